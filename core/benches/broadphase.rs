@@ -0,0 +1,98 @@
+#![feature(test)]
+
+extern crate test;
+extern crate shootr;
+
+use test::Bencher;
+use shootr::collision::{Broadphase, Bounds, Grid, QuadTree, SweepAndPrune, BruteForce};
+use shootr::model::game::Vector;
+
+const WORLD_SIZE: i32 = 10_000;
+
+// Deterministic xorshift, so every backend gets the very same layout
+struct Layout {
+    state: u32,
+}
+impl Layout {
+    fn next(&mut self, max: i32) -> i32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        (self.state % max as u32) as i32
+    }
+}
+
+fn populate<B>(world: &mut B, count: u32)
+where
+    B: Broadphase<u32>,
+{
+    let mut layout = Layout { state: 0x2545_f491 };
+    for id in 0..count {
+        let bounds = Bounds {
+            x: 1 + layout.next(WORLD_SIZE - 2),
+            y: 1 + layout.next(WORLD_SIZE - 2),
+            width: 5 + layout.next(75),
+            height: 5 + layout.next(75),
+        };
+        world.add(id, bounds);
+    }
+}
+
+fn bench_intersects<B>(bencher: &mut Bencher, mut world: B, count: u32)
+where
+    B: Broadphase<u32>,
+{
+    populate(&mut world, count);
+    bencher.iter(|| {
+        let mut collisions = 0;
+        world.query_intersects(|_, _| collisions += 1);
+        collisions
+    });
+}
+
+fn bench_place<B>(bencher: &mut Bencher, mut world: B, count: u32)
+where
+    B: Broadphase<u32>,
+{
+    populate(&mut world, count);
+    let mut offset = 1;
+    bencher.iter(|| {
+        offset = -offset;
+        for id in 0..count {
+            let (x, y) = {
+                let bounds = world.get(&id).unwrap();
+                (bounds.x + offset, bounds.y + offset)
+            };
+            world.place(&id, &Vector { x, y }.into());
+        }
+    });
+}
+
+macro_rules! bench_backend {
+    ( $intersects:ident, $place:ident, $count:expr, $new:expr ) => {
+        #[bench]
+        fn $intersects(bencher: &mut Bencher) {
+            bench_intersects(bencher, $new, $count);
+        }
+        #[bench]
+        fn $place(bencher: &mut Bencher) {
+            bench_place(bencher, $new, $count);
+        }
+    };
+}
+
+bench_backend!(grid_intersects_10, grid_place_10, 10, Grid::new(WORLD_SIZE, WORLD_SIZE, 100));
+bench_backend!(grid_intersects_1000, grid_place_1000, 1000, Grid::new(WORLD_SIZE, WORLD_SIZE, 100));
+bench_backend!(grid_intersects_100000, grid_place_100000, 100_000, Grid::new(WORLD_SIZE, WORLD_SIZE, 100));
+
+bench_backend!(quadtree_intersects_10, quadtree_place_10, 10, QuadTree::new(WORLD_SIZE, WORLD_SIZE));
+bench_backend!(quadtree_intersects_1000, quadtree_place_1000, 1000, QuadTree::new(WORLD_SIZE, WORLD_SIZE));
+bench_backend!(quadtree_intersects_100000, quadtree_place_100000, 100_000, QuadTree::new(WORLD_SIZE, WORLD_SIZE));
+
+bench_backend!(sweep_and_prune_intersects_10, sweep_and_prune_place_10, 10, SweepAndPrune::new(WORLD_SIZE, WORLD_SIZE));
+bench_backend!(sweep_and_prune_intersects_1000, sweep_and_prune_place_1000, 1000, SweepAndPrune::new(WORLD_SIZE, WORLD_SIZE));
+bench_backend!(sweep_and_prune_intersects_100000, sweep_and_prune_place_100000, 100_000, SweepAndPrune::new(WORLD_SIZE, WORLD_SIZE));
+
+// Checking 100,000 entities pairwise takes minutes per iteration, so the reference stops at 1,000
+bench_backend!(brute_force_intersects_10, brute_force_place_10, 10, BruteForce::new(WORLD_SIZE, WORLD_SIZE));
+bench_backend!(brute_force_intersects_1000, brute_force_place_1000, 1000, BruteForce::new(WORLD_SIZE, WORLD_SIZE));
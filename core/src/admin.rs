@@ -0,0 +1,359 @@
+use model::comp::Team;
+use model::game::Id;
+use model::network::Latency;
+use results::{MatchResult, LeaderboardEntry};
+use std::io::{self, BufRead};
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// Matches listed by `matches` without an explicit count
+const DEFAULT_MATCHES: usize = 5;
+/// Players listed by `leaderboard` without an explicit count
+const DEFAULT_LEADERS: usize = 10;
+
+/// Commands that can be typed into the console of the server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdminCommand {
+    /// Lists every connected player
+    Status,
+    /// Stops the simulation while still sending the state to clients
+    Pause,
+    /// Lifts any pause, including the one the players agreed on
+    Resume,
+    /// Pauses and advances the simulation by the given amount of updates
+    Step(u32),
+    /// Stops the server, telling the players the optional reason
+    Shutdown(Option<String>),
+    /// Lists the given amount of the most recent matches
+    Matches(usize),
+    /// Lists the given amount of players with the highest total score
+    Leaderboard(usize),
+    /// Lists the given amount of players with the highest skill rating
+    Ratings(usize),
+}
+
+impl FromStr for AdminCommand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s == "shutdown" || s.starts_with("shutdown ") {
+            let reason = s["shutdown".len()..].trim();
+            let reason = if reason.is_empty() {
+                None
+            } else {
+                Some(reason.to_owned())
+            };
+            return Ok(AdminCommand::Shutdown(reason));
+        }
+        let mut words = s.split_whitespace();
+        let command = match (words.next(), words.next()) {
+            (Some("status"), None) => AdminCommand::Status,
+            (Some("pause"), None) => AdminCommand::Pause,
+            (Some("resume"), None) => AdminCommand::Resume,
+            (Some("step"), None) => AdminCommand::Step(1),
+            (Some("step"), Some(steps)) => AdminCommand::Step(parse_count(steps, "steps")?),
+            (Some("matches"), None) => AdminCommand::Matches(DEFAULT_MATCHES),
+            (Some("matches"), Some(count)) => AdminCommand::Matches(parse_count(count, "matches")?),
+            (Some("leaderboard"), None) => AdminCommand::Leaderboard(DEFAULT_LEADERS),
+            (Some("leaderboard"), Some(count)) => {
+                AdminCommand::Leaderboard(parse_count(count, "players")?)
+            }
+            (Some("ratings"), None) => AdminCommand::Ratings(DEFAULT_LEADERS),
+            (Some("ratings"), Some(count)) => AdminCommand::Ratings(parse_count(count, "players")?),
+            _ => return Err(format!("Unknown command: {}", s)),
+        };
+        if words.next().is_some() {
+            return Err(format!("Too many arguments: {}", s));
+        }
+        Ok(command)
+    }
+}
+
+fn parse_count<T: FromStr>(count: &str, what: &str) -> Result<T, String> {
+    count.parse().map_err(
+        |_| format!("Invalid amount of {}: {}", what, count),
+    )
+}
+
+/// Reads commands from stdin on a separate thread
+pub fn start_console() -> Receiver<AdminCommand> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let stdin = io::stdin();
+        for line in stdin.lock().lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            match line.parse() {
+                Ok(command) => {
+                    if sender.send(command).is_err() {
+                        break;
+                    }
+                }
+                Err(err) => println!("{}", err),
+            }
+        }
+    });
+    receiver
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerStatus {
+    pub id: Id,
+    pub name: Option<String>,
+    pub team: Option<Team>,
+    pub score: Option<u32>,
+    /// Missing until the client answered its first ping
+    pub latency: Option<Latency>,
+}
+
+/// Names are shown next to the id, as they don't have to be unique
+fn describe(id: &Id, name: &Option<String>) -> String {
+    match *name {
+        Some(ref name) => format!("{} ({})", id, name),
+        None => id.to_string(),
+    }
+}
+
+pub fn format_status(players: &[PlayerStatus]) -> String {
+    let mut lines = vec![format!("{} player(s) connected", players.len())];
+    for player in players {
+        let team = player.team.map_or("-".to_owned(), |team| team.0.to_string());
+        let score = player.score.map_or("-".to_owned(), |score| score.to_string());
+        let latency = match player.latency {
+            Some(ref latency) => {
+                format!(
+                    "rtt {:.0}ms, clock offset {:.0}ms",
+                    latency.rtt,
+                    latency.clock_offset
+                )
+            }
+            None => "rtt unknown".to_owned(),
+        };
+        lines.push(format!(
+            "{}: team {}, score {}, {}",
+            describe(&player.id, &player.name),
+            team,
+            score,
+            latency
+        ));
+    }
+    lines.join("\n")
+}
+
+pub fn format_matches(matches: &[MatchResult]) -> String {
+    let mut lines = vec![format!("{} match(es) recorded", matches.len())];
+    for result in matches {
+        lines.push(format!(
+            "{:?}, finished at {}, lasted {}s, {} player(s)",
+            result.mode,
+            result.finished,
+            result.duration() / 1000,
+            result.players.len()
+        ));
+        for player in &result.players {
+            let team = player.team.map_or("-".to_owned(), |team| team.0.to_string());
+            let score = player.score.map_or("-".to_owned(), |score| score.to_string());
+            let left = if player.disconnected { ", left early" } else { "" };
+            lines.push(format!(
+                "  {}: team {}, score {}, {} hit(s), {} death(s){}",
+                describe(&player.id, &player.name),
+                team,
+                score,
+                player.hits,
+                player.deaths,
+                left
+            ));
+        }
+    }
+    lines.join("\n")
+}
+
+pub fn format_leaderboard(entries: &[LeaderboardEntry]) -> String {
+    let mut lines = vec![format!("{} player(s) ranked", entries.len())];
+    for (rank, entry) in entries.iter().enumerate() {
+        lines.push(format!(
+            "{}. {}: score {}, {} hit(s), {} death(s), {} match(es), {} left early",
+            rank + 1,
+            entry.name,
+            entry.score,
+            entry.hits,
+            entry.deaths,
+            entry.matches,
+            entry.disconnects
+        ));
+    }
+    lines.join("\n")
+}
+
+pub fn format_ratings(ratings: &[(String, f64)]) -> String {
+    let mut lines = vec![format!("{} player(s) rated", ratings.len())];
+    for (rank, &(ref name, rating)) in ratings.iter().enumerate() {
+        lines.push(format!("{}. {}: {:.0}", rank + 1, name, rating));
+    }
+    lines.join("\n")
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use model::game::GameMode;
+    use results::PlayerResult;
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(Ok(AdminCommand::Status), "status".parse());
+        assert_eq!(Ok(AdminCommand::Status), "  status\n".parse());
+        assert_eq!(Ok(AdminCommand::Pause), "pause".parse());
+        assert_eq!(Ok(AdminCommand::Resume), "resume".parse());
+        assert!("stat".parse::<AdminCommand>().is_err());
+        assert!("status now".parse::<AdminCommand>().is_err());
+    }
+
+    #[test]
+    fn parse_steps() {
+        assert_eq!(Ok(AdminCommand::Step(1)), "step".parse());
+        assert_eq!(Ok(AdminCommand::Step(5)), "step 5".parse());
+        assert!("step five".parse::<AdminCommand>().is_err());
+        assert!("step -1".parse::<AdminCommand>().is_err());
+        assert!("step 1 2".parse::<AdminCommand>().is_err());
+    }
+
+    #[test]
+    fn parse_shutdown() {
+        assert_eq!(Ok(AdminCommand::Shutdown(None)), "shutdown".parse());
+        let reason = "Restarting for an update".to_owned();
+        assert_eq!(
+            Ok(AdminCommand::Shutdown(Some(reason))),
+            "shutdown  Restarting for an update ".parse()
+        );
+        assert!("shutdownnow".parse::<AdminCommand>().is_err());
+    }
+
+    #[test]
+    fn parse_queries() {
+        assert_eq!(Ok(AdminCommand::Matches(DEFAULT_MATCHES)), "matches".parse());
+        assert_eq!(Ok(AdminCommand::Matches(3)), "matches 3".parse());
+        assert_eq!(
+            Ok(AdminCommand::Leaderboard(DEFAULT_LEADERS)),
+            "leaderboard".parse()
+        );
+        assert_eq!(Ok(AdminCommand::Leaderboard(20)), "leaderboard 20".parse());
+        assert!("matches all".parse::<AdminCommand>().is_err());
+        assert!("leaderboard 1 2".parse::<AdminCommand>().is_err());
+        assert_eq!(Ok(AdminCommand::Ratings(DEFAULT_LEADERS)), "ratings".parse());
+        assert_eq!(Ok(AdminCommand::Ratings(3)), "ratings 3".parse());
+    }
+
+    #[test]
+    fn format_empty_status() {
+        assert_eq!("0 player(s) connected", format_status(&[]));
+    }
+
+    #[test]
+    fn format_players() {
+        let id = Id::nil();
+        let players = [
+            PlayerStatus {
+                id,
+                name: Some("Ann".to_owned()),
+                team: Some(Team(1)),
+                score: Some(3),
+                latency: Some(Latency {
+                    rtt: 41.6,
+                    clock_offset: -12.2,
+                    samples: 4,
+                }),
+            },
+            PlayerStatus {
+                id,
+                name: None,
+                team: None,
+                score: None,
+                latency: None,
+            },
+        ];
+        let expected = format!(
+            "2 player(s) connected\n\
+             {0} (Ann): team 1, score 3, rtt 42ms, clock offset -12ms\n\
+             {0}: team -, score -, rtt unknown",
+            id
+        );
+        assert_eq!(expected, format_status(&players));
+    }
+
+    #[test]
+    fn format_recent_matches() {
+        let id = Id::nil();
+        let matches = [
+            MatchResult {
+                started: 1000,
+                finished: 61_000,
+                mode: GameMode::Pong,
+                players: vec![
+                    PlayerResult {
+                        id,
+                        name: Some("Bob".to_owned()),
+                        team: Some(Team(0)),
+                        score: Some(5),
+                        hits: 12,
+                        deaths: 0,
+                        disconnected: false,
+                    },
+                    PlayerResult {
+                        id,
+                        name: None,
+                        team: None,
+                        score: None,
+                        hits: 0,
+                        deaths: 2,
+                        disconnected: true,
+                    },
+                ],
+            },
+        ];
+        let expected = format!(
+            "1 match(es) recorded\n\
+             Pong, finished at 61000, lasted 60s, 2 player(s)\n  \
+             {0} (Bob): team 0, score 5, 12 hit(s), 0 death(s)\n  \
+             {0}: team -, score -, 0 hit(s), 2 death(s), left early",
+            id
+        );
+        assert_eq!(expected, format_matches(&matches));
+    }
+
+    #[test]
+    fn format_ranks() {
+        let entries = [
+            LeaderboardEntry {
+                name: "Cid".to_owned(),
+                matches: 3,
+                score: 14,
+                hits: 40,
+                deaths: 1,
+                disconnects: 1,
+            },
+        ];
+        assert_eq!(
+            "1 player(s) ranked\n\
+             1. Cid: score 14, 40 hit(s), 1 death(s), 3 match(es), 1 left early",
+            format_leaderboard(&entries)
+        );
+    }
+
+    #[test]
+    fn format_top_ratings() {
+        let ratings = [("Ann".to_owned(), 1612.4), ("Bob".to_owned(), 1388.0)];
+        assert_eq!(
+            "2 player(s) rated\n1. Ann: 1612\n2. Bob: 1388",
+            format_ratings(&ratings)
+        );
+    }
+}
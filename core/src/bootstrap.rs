@@ -2,15 +2,20 @@ extern crate specs;
 
 use self::specs::World;
 use model::comp::*;
-use model::game::Vector;
+use model::game::{Vector, Side, Id};
 use util::SeqIdGen;
+use system::{CollisionEvents, DamageEvents};
+use collision::World as CollisionWorld;
+use level::Level;
+use model::network::Shutdown;
 
 use std::sync::RwLock;
 
-pub fn prepare_world(world: &mut World) {
+pub fn prepare_world(world: &mut World, level: Level) {
     register_components(world);
-    add_constraints(world);
+    add_constraints(world, &level);
     add_utils(world);
+    add_level(world, level);
 }
 
 
@@ -30,13 +35,27 @@ fn register_components(world: &mut World) {
         Bounciness,
         Player,
         Friction,
+        Health,
+        Score,
+        Side,
+        Team,
+        Name,
+        Damage,
+        Respawn,
+        Weapon,
+        Projectile,
+        Size,
+        Pickup,
+        LastHitter,
+        Stats,
+        Effects,
         ToSpawn,
         ToDespawn,
         Actor,
     );
 }
 
-fn add_constraints(world: &mut World) {
+fn add_constraints(world: &mut World, level: &Level) {
     world.add_resource(Bounds {
         min: Acc::from(Vector { x: -5, y: -5 }),
         max: Acc::from(Vector { x: 5, y: 5 }),
@@ -47,10 +66,24 @@ fn add_constraints(world: &mut World) {
     });
     world.add_resource(Bounds {
         min: Pos::from(Vector { x: 0, y: 0 }),
-        max: Pos::from(Vector { x: 1000, y: 1000 }),
+        max: Pos::from(Vector {
+            x: level.width,
+            y: level.height,
+        }),
     });
 }
 
 fn add_utils(world: &mut World) {
     world.add_resource(RwLock::new(SeqIdGen::default()));
+    world.add_resource(CollisionEvents::new());
+    world.add_resource(DamageEvents::new());
+    world.add_resource(Shutdown::default());
+}
+
+fn add_level(world: &mut World, level: Level) {
+    let mut collision_world = CollisionWorld::<Id>::new(level.width, level.height, 100);
+    let goal_zones = level.populate(&mut collision_world);
+    world.add_resource(RwLock::new(collision_world));
+    world.add_resource(goal_zones);
+    world.add_resource(level);
 }
@@ -0,0 +1,282 @@
+use model::game::Id;
+use util::Time;
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+
+/// Room every client chats in until they join another one
+pub const DEFAULT_ROOM: &str = "lobby";
+const MAX_ROOM_LENGTH: usize = 16;
+const ROOM_CHARACTERS: &str = "abcdefghijklmnopqrstuvwxyz0123456789-_";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatConfig {
+    /// Characters a single message may have
+    pub max_length: usize,
+    /// Messages a client may send within `window`
+    pub messages_per_window: usize,
+    /// Length of the rate limiting window in ms
+    pub window: Time,
+    /// Lowercase words that get replaced by asterisks
+    pub blocked_words: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChatError {
+    Empty,
+    TooLong,
+    RateLimited,
+    InvalidRoom,
+}
+
+impl fmt::Display for ChatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ChatError::Empty => write!(f, "Message is empty"),
+            ChatError::TooLong => write!(f, "Message is too long"),
+            ChatError::RateLimited => write!(f, "Too many messages, please wait a moment"),
+            ChatError::InvalidRoom => write!(f, "Room names may only contain a-z, 0-9, - and _"),
+        }
+    }
+}
+
+impl Error for ChatError {
+    fn description(&self) -> &str {
+        match *self {
+            ChatError::Empty => "Message is empty",
+            ChatError::TooLong => "Message is too long",
+            ChatError::RateLimited => "Too many messages",
+            ChatError::InvalidRoom => "Invalid room name",
+        }
+    }
+}
+
+/// Message that passed every check and can be sent to the members of `room`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Post {
+    pub room: String,
+    pub text: String,
+}
+
+/// Rooms and rate limits of every connected client
+#[derive(Debug, Clone)]
+pub struct Chat {
+    config: ChatConfig,
+    rooms: HashMap<Id, String>,
+    /// Times at which each client sent their latest messages
+    sent: HashMap<Id, VecDeque<Time>>,
+}
+
+impl Chat {
+    pub fn new(config: ChatConfig) -> Self {
+        Chat {
+            config,
+            rooms: HashMap::new(),
+            sent: HashMap::new(),
+        }
+    }
+
+    pub fn connect(&mut self, id: Id) {
+        self.rooms.insert(id, DEFAULT_ROOM.to_owned());
+    }
+
+    pub fn disconnect(&mut self, id: &Id) {
+        self.rooms.remove(id);
+        self.sent.remove(id);
+    }
+
+    /// Moves `id` to `room` and returns the room's name as it gets shown to everybody
+    pub fn join(&mut self, id: Id, room: &str) -> Result<String, ChatError> {
+        let room = validate_room(room)?;
+        self.rooms.insert(id, room.clone());
+        Ok(room)
+    }
+
+    /// Checks a message `id` sent at `now` and cleans it up
+    pub fn post(&mut self, id: Id, text: &str, now: Time) -> Result<Post, ChatError> {
+        let room = match self.rooms.get(&id) {
+            Some(room) => room.clone(),
+            // Only connected clients can chat
+            None => return Err(ChatError::InvalidRoom),
+        };
+        let text = clean(text, &self.config)?;
+        let window = self.config.window;
+        let sent = self.sent.entry(id).or_insert_with(VecDeque::new);
+        while sent.front().map_or(false, |&time| time + window <= now) {
+            sent.pop_front();
+        }
+        if sent.len() >= self.config.messages_per_window {
+            return Err(ChatError::RateLimited);
+        }
+        sent.push_back(now);
+        Ok(Post { room, text })
+    }
+
+    /// Clients that receive what gets posted to `room`
+    pub fn members(&self, room: &str) -> Vec<Id> {
+        self.rooms
+            .iter()
+            .filter(|&(_, joined)| joined == room)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+}
+
+fn validate_room(room: &str) -> Result<String, ChatError> {
+    let room = room.trim().to_lowercase();
+    let is_valid = !room.is_empty() && room.chars().count() <= MAX_ROOM_LENGTH &&
+        room.chars().all(|c| ROOM_CHARACTERS.contains(c));
+    if is_valid {
+        Ok(room)
+    } else {
+        Err(ChatError::InvalidRoom)
+    }
+}
+
+/// Strips control characters and whitespace around the text and censors blocked words
+fn clean(text: &str, config: &ChatConfig) -> Result<String, ChatError> {
+    let text = text.chars()
+        .filter(|c| !c.is_control())
+        .collect::<String>();
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(ChatError::Empty);
+    }
+    if text.chars().count() > config.max_length {
+        return Err(ChatError::TooLong);
+    }
+    Ok(censor(text, &config.blocked_words))
+}
+
+/// Replaces every character of whole words in `blocked` by an asterisk, ignoring case
+fn censor(text: &str, blocked: &[String]) -> String {
+    let mut censored = String::with_capacity(text.len());
+    let mut word = String::new();
+    for c in text.chars().chain(Some(' ')) {
+        if c.is_alphanumeric() {
+            word.push(c);
+            continue;
+        }
+        if blocked.contains(&word.to_lowercase()) {
+            censored.extend(word.chars().map(|_| '*'));
+        } else {
+            censored.push_str(&word);
+        }
+        word.clear();
+        censored.push(c);
+    }
+    // Drop the space that flushed the last word
+    censored.pop();
+    censored
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn config() -> ChatConfig {
+        ChatConfig {
+            max_length: 20,
+            messages_per_window: 2,
+            window: 1000,
+            blocked_words: vec!["darn".to_owned(), "heck".to_owned()],
+        }
+    }
+
+    fn connected(id: Id) -> Chat {
+        let mut chat = Chat::new(config());
+        chat.connect(id);
+        chat
+    }
+
+    #[test]
+    fn post_to_default_room() {
+        let id = Id::new_v4();
+        let mut chat = connected(id);
+        let post = chat.post(id, "  Hello!\n", 0).unwrap();
+        assert_eq!(DEFAULT_ROOM, post.room);
+        assert_eq!("Hello!", post.text);
+    }
+
+    #[test]
+    fn reject_empty_and_long_messages() {
+        let id = Id::new_v4();
+        let mut chat = connected(id);
+        assert_eq!(Err(ChatError::Empty), chat.post(id, " \t", 0));
+        assert_eq!(Err(ChatError::Empty), chat.post(id, "\u{7}", 0));
+        assert_eq!(Err(ChatError::TooLong), chat.post(id, &"a".repeat(21), 0));
+        assert!(chat.post(id, &"ä".repeat(20), 0).is_ok());
+    }
+
+    #[test]
+    fn disconnected_clients_cant_post() {
+        let id = Id::new_v4();
+        let mut chat = connected(id);
+        chat.disconnect(&id);
+        assert_eq!(Err(ChatError::InvalidRoom), chat.post(id, "Hi", 0));
+    }
+
+    #[test]
+    fn rate_limit_per_window() {
+        let id = Id::new_v4();
+        let other = Id::new_v4();
+        let mut chat = connected(id);
+        chat.connect(other);
+        assert!(chat.post(id, "1", 0).is_ok());
+        assert!(chat.post(id, "2", 500).is_ok());
+        assert_eq!(Err(ChatError::RateLimited), chat.post(id, "3", 999));
+        // Others are limited separately
+        assert!(chat.post(other, "1", 999).is_ok());
+        // The first message left the window
+        assert!(chat.post(id, "3", 1000).is_ok());
+        assert_eq!(Err(ChatError::RateLimited), chat.post(id, "4", 1200));
+    }
+
+    #[test]
+    fn rejected_messages_dont_count() {
+        let id = Id::new_v4();
+        let mut chat = connected(id);
+        for _ in 0..5 {
+            assert!(chat.post(id, "", 0).is_err());
+        }
+        assert!(chat.post(id, "Hi", 0).is_ok());
+    }
+
+    #[test]
+    fn censor_blocked_words() {
+        let id = Id::new_v4();
+        let mut chat = connected(id);
+        assert_eq!("Oh ****, ****!", chat.post(id, "Oh DARN, heck!", 0).unwrap().text);
+        // Only whole words are blocked
+        assert_eq!("darned checks", chat.post(id, "darned checks", 0).unwrap().text);
+    }
+
+    #[test]
+    fn scope_messages_to_rooms() {
+        let (ann, bob, cid) = (Id::new_v4(), Id::new_v4(), Id::new_v4());
+        let mut chat = Chat::new(config());
+        for id in &[ann, bob, cid] {
+            chat.connect(*id);
+        }
+        assert_eq!(Ok("red-team".to_owned()), chat.join(ann, " Red-Team "));
+        chat.join(bob, "red-team").unwrap();
+        assert_eq!("red-team", chat.post(ann, "Go", 0).unwrap().room);
+        let mut members = chat.members("red-team");
+        members.sort();
+        let mut expected = vec![ann, bob];
+        expected.sort();
+        assert_eq!(expected, members);
+        assert_eq!(vec![cid], chat.members(DEFAULT_ROOM));
+    }
+
+    #[test]
+    fn reject_invalid_rooms() {
+        let id = Id::new_v4();
+        let mut chat = connected(id);
+        assert_eq!(Err(ChatError::InvalidRoom), chat.join(id, ""));
+        assert_eq!(Err(ChatError::InvalidRoom), chat.join(id, "red team"));
+        assert_eq!(Err(ChatError::InvalidRoom), chat.join(id, &"a".repeat(17)));
+        assert_eq!(DEFAULT_ROOM, chat.post(id, "Hi", 0).unwrap().room);
+    }
+}
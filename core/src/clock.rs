@@ -0,0 +1,328 @@
+use util::SeqId;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+/// Source of the current time
+pub trait Clock {
+    /// Time passed since an arbitrary but fixed point
+    fn now(&self) -> Duration;
+}
+
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        SystemClock { start: Instant::now() }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// Clock that only moves when told to. Clones share the same time.
+#[derive(Debug, Clone, Default)]
+pub struct MockClock {
+    now: Arc<RwLock<Duration>>,
+}
+
+impl MockClock {
+    pub fn advance(&self, by: Duration) {
+        *self.now.write().unwrap() += by;
+    }
+
+    pub fn set(&self, now: Duration) {
+        *self.now.write().unwrap() = now;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Duration {
+        *self.now.read().unwrap()
+    }
+}
+
+/// Fixed timestep of the simulation, decoupled from how often the game loop actually runs
+pub struct GameClock {
+    clock: Box<Clock + Send + Sync>,
+    /// Length of a single update in ns
+    dt: u64,
+    /// Updates that are run at most to catch up with the clock.
+    /// Any time beyond that is dropped, so a slow server can't fall further and further behind.
+    max_catch_up: u32,
+    previous: Duration,
+    /// Time in ns that has not been simulated yet
+    lag: u64,
+    tick: SeqId,
+    send_tick: SeqId,
+    paused_by_admin: bool,
+    paused_by_players: bool,
+    /// Updates to run while paused
+    pending_steps: u32,
+}
+
+impl GameClock {
+    pub fn new<C>(clock: C, updates_per_sec: u32, max_catch_up: u32) -> Self
+    where
+        C: Clock + Send + Sync + 'static,
+    {
+        assert!(updates_per_sec > 0, "Tried to create a clock without any updates");
+        let previous = clock.now();
+        GameClock {
+            clock: Box::new(clock),
+            dt: NANOS_PER_SEC / u64::from(updates_per_sec),
+            max_catch_up,
+            previous,
+            lag: 0,
+            tick: 0,
+            send_tick: 0,
+            paused_by_admin: false,
+            paused_by_players: false,
+            pending_steps: 0,
+        }
+    }
+
+    pub fn dt(&self) -> Duration {
+        from_nanos(self.dt)
+    }
+
+    /// Number of the update that is currently simulated
+    pub fn tick(&self) -> SeqId {
+        self.tick
+    }
+
+    /// Number of the world updates that have been sent to clients so far
+    pub fn send_tick(&self) -> SeqId {
+        self.send_tick
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_by_admin || self.paused_by_players
+    }
+
+    pub fn set_paused_by_admin(&mut self, paused: bool) {
+        self.paused_by_admin = paused;
+    }
+
+    pub fn set_paused_by_players(&mut self, paused: bool) {
+        self.paused_by_players = paused;
+    }
+
+    /// Pauses the simulation and runs exactly `steps` updates,
+    /// spread over several game loop iterations if there are more than `max_catch_up`
+    pub fn step(&mut self, steps: u32) {
+        self.paused_by_admin = true;
+        self.pending_steps = self.pending_steps.saturating_add(steps);
+    }
+
+    /// Catches up with the clock and returns how many updates are due
+    pub fn due_updates(&mut self) -> u32 {
+        let now = self.clock.now();
+        // A clock going backwards just means that no time passed
+        if let Some(elapsed) = now.checked_sub(self.previous) {
+            self.lag += to_nanos(elapsed);
+        }
+        self.previous = now;
+
+        if self.is_paused() {
+            // Time doesn't pass for a paused simulation
+            self.lag = 0;
+            let steps = self.pending_steps.min(self.max_catch_up);
+            self.pending_steps -= steps;
+            return steps;
+        }
+        self.pending_steps = 0;
+
+        let due = self.lag / self.dt;
+        if due > u64::from(self.max_catch_up) {
+            self.lag %= self.dt;
+            self.max_catch_up
+        } else {
+            self.lag -= due * self.dt;
+            due as u32
+        }
+    }
+
+    pub fn finish_update(&mut self) {
+        self.tick += 1;
+    }
+
+    pub fn finish_send(&mut self) {
+        self.send_tick += 1;
+    }
+
+    /// Time the game loop can sleep until the next update is due
+    pub fn until_next_update(&self) -> Duration {
+        let now = self.clock.now();
+        let elapsed = now.checked_sub(self.previous).map_or(0, to_nanos);
+        from_nanos(self.dt.saturating_sub(self.lag + elapsed))
+    }
+}
+
+fn to_nanos(duration: Duration) -> u64 {
+    duration.as_secs() * NANOS_PER_SEC + u64::from(duration.subsec_nanos())
+}
+
+fn from_nanos(nanos: u64) -> Duration {
+    Duration::new(nanos / NANOS_PER_SEC, (nanos % NANOS_PER_SEC) as u32)
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    fn game_clock(max_catch_up: u32) -> (GameClock, MockClock) {
+        let mock = MockClock::default();
+        (GameClock::new(mock.clone(), 50, max_catch_up), mock)
+    }
+
+    #[test]
+    fn fixed_dt() {
+        let (clock, _) = game_clock(5);
+        assert_eq!(ms(20), clock.dt());
+        let clock = GameClock::new(MockClock::default(), 30, 5);
+        assert_eq!(Duration::new(0, 33_333_333), clock.dt());
+    }
+
+    #[test]
+    fn no_updates_without_time_passing() {
+        let (mut clock, _) = game_clock(5);
+        assert_eq!(0, clock.due_updates());
+    }
+
+    #[test]
+    fn one_update_per_dt() {
+        let (mut clock, mock) = game_clock(5);
+        mock.advance(ms(20));
+        assert_eq!(1, clock.due_updates());
+        mock.advance(ms(60));
+        assert_eq!(3, clock.due_updates());
+    }
+
+    #[test]
+    fn keep_remaining_time() {
+        let (mut clock, mock) = game_clock(5);
+        mock.advance(ms(30));
+        assert_eq!(1, clock.due_updates());
+        mock.advance(ms(5));
+        assert_eq!(0, clock.due_updates());
+        mock.advance(ms(5));
+        assert_eq!(1, clock.due_updates());
+    }
+
+    #[test]
+    fn limit_catch_up() {
+        let (mut clock, mock) = game_clock(5);
+        mock.advance(ms(1010));
+        assert_eq!(5, clock.due_updates());
+        // The time beyond the catch up is dropped, only the remainder is kept
+        mock.advance(ms(10));
+        assert_eq!(1, clock.due_updates());
+    }
+
+    #[test]
+    fn ignore_clock_going_backwards() {
+        let (mut clock, mock) = game_clock(5);
+        mock.set(ms(100));
+        assert_eq!(5, clock.due_updates());
+        mock.set(ms(50));
+        assert_eq!(0, clock.due_updates());
+        mock.set(ms(70));
+        assert_eq!(1, clock.due_updates());
+    }
+
+    #[test]
+    fn count_ticks_separately() {
+        let (mut clock, _) = game_clock(5);
+        clock.finish_update();
+        clock.finish_update();
+        clock.finish_send();
+        assert_eq!(2, clock.tick());
+        assert_eq!(1, clock.send_tick());
+    }
+
+    #[test]
+    fn no_updates_while_paused() {
+        let (mut clock, mock) = game_clock(5);
+        clock.set_paused_by_admin(true);
+        mock.advance(ms(100));
+        assert_eq!(0, clock.due_updates());
+        assert!(clock.is_paused());
+
+        clock.set_paused_by_admin(false);
+        assert_eq!(0, clock.due_updates());
+        mock.advance(ms(20));
+        assert_eq!(1, clock.due_updates());
+    }
+
+    #[test]
+    fn pause_by_admin_or_players() {
+        let (mut clock, _) = game_clock(5);
+        assert!(!clock.is_paused());
+        clock.set_paused_by_players(true);
+        assert!(clock.is_paused());
+        clock.set_paused_by_admin(true);
+        clock.set_paused_by_players(false);
+        assert!(clock.is_paused());
+        clock.set_paused_by_admin(false);
+        assert!(!clock.is_paused());
+    }
+
+    #[test]
+    fn single_step() {
+        let (mut clock, mock) = game_clock(5);
+        clock.step(1);
+        assert!(clock.is_paused());
+        assert_eq!(1, clock.due_updates());
+        mock.advance(ms(100));
+        assert_eq!(0, clock.due_updates());
+
+        clock.step(2);
+        clock.step(1);
+        assert_eq!(3, clock.due_updates());
+    }
+
+    #[test]
+    fn spread_steps_over_iterations() {
+        let (mut clock, _) = game_clock(5);
+        clock.step(12);
+        assert_eq!(5, clock.due_updates());
+        assert_eq!(5, clock.due_updates());
+        assert_eq!(2, clock.due_updates());
+        assert_eq!(0, clock.due_updates());
+
+        clock.step(u32::max_value());
+        clock.step(1);
+        assert_eq!(5, clock.due_updates());
+        // Resuming drops the remaining steps
+        clock.set_paused_by_admin(false);
+        assert_eq!(0, clock.due_updates());
+        clock.set_paused_by_admin(true);
+        assert_eq!(0, clock.due_updates());
+    }
+
+    #[test]
+    fn wait_until_next_update() {
+        let (mut clock, mock) = game_clock(5);
+        assert_eq!(ms(20), clock.until_next_update());
+        mock.advance(ms(25));
+        clock.due_updates();
+        assert_eq!(ms(15), clock.until_next_update());
+        mock.advance(ms(10));
+        assert_eq!(ms(5), clock.until_next_update());
+        // Overdue updates don't make the wait negative
+        mock.advance(ms(100));
+        assert_eq!(ms(0), clock.until_next_update());
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use model::comp::Pos;
+use super::{Bounds, Filter, CollisionObject, Broadphase, assert_in_world,
+            assert_valid_dimensions, report_if_intersecting};
+
+/// Checks every entity against every other one.
+/// Too slow for the game, but trivially correct, which makes it a good reference.
+pub struct BruteForce<Id> {
+    width: i32,
+    height: i32,
+    ids: Vec<Id>,
+    entities: HashMap<Id, Bounds>,
+    filters: HashMap<Id, Filter>,
+}
+
+impl<Id> BruteForce<Id>
+where
+    Id: Hash + Eq + Clone,
+{
+    pub fn new(width: i32, height: i32) -> Self {
+        assert_valid_dimensions(width, height);
+        BruteForce {
+            width,
+            height,
+            ids: Vec::new(),
+            entities: HashMap::new(),
+            filters: HashMap::new(),
+        }
+    }
+
+    fn object<'a>(&'a self, id: &'a Id) -> CollisionObject<'a, Id> {
+        CollisionObject {
+            id,
+            bounds: &self.entities[id],
+            filter: &self.filters[id],
+        }
+    }
+}
+
+impl<Id> Broadphase<Id> for BruteForce<Id>
+where
+    Id: Hash + Eq + Clone,
+{
+    fn width(&self) -> i32 {
+        self.width
+    }
+
+    fn height(&self) -> i32 {
+        self.height
+    }
+
+    fn get(&self, id: &Id) -> Option<&Bounds> {
+        self.entities.get(id)
+    }
+
+    fn get_filter(&self, id: &Id) -> Option<&Filter> {
+        self.filters.get(id)
+    }
+
+    fn add_filtered(&mut self, id: Id, bounds: Bounds, filter: Filter) {
+        assert_in_world(self.width, self.height, &bounds);
+        let old = self.entities.insert(id.clone(), bounds);
+        assert!(
+            old.is_none(),
+            "Failed to add new entity: Id already registered"
+        );
+        self.filters.insert(id.clone(), filter);
+        self.ids.push(id);
+    }
+
+    fn place(&mut self, id: &Id, pos: &Pos) {
+        let mut bounds = self.entities
+            .get(id)
+            .expect("Failed to place entity: Id doesn't exist")
+            .clone();
+        bounds.x = pos.x;
+        bounds.y = pos.y;
+        assert_in_world(self.width, self.height, &bounds);
+        self.entities.insert(id.clone(), bounds);
+    }
+
+    fn remove(&mut self, id: &Id) -> Option<Bounds> {
+        let bounds = self.entities.remove(id);
+        if bounds.is_some() {
+            self.filters.remove(id);
+            let pos = self.ids.iter().position(|x| *x == *id).expect(
+                "Didn't find id in id list",
+            );
+            self.ids.remove(pos);
+        }
+        bounds
+    }
+
+    fn query_intersects<T>(&self, mut cb: T)
+    where
+        T: FnMut(CollisionObject<Id>, CollisionObject<Id>),
+    {
+        for (i, id) in self.ids.iter().enumerate() {
+            for other_id in &self.ids[i + 1..] {
+                report_if_intersecting(self.object(id), self.object(other_id), &mut cb);
+            }
+        }
+    }
+
+    fn query_candidates<T>(&self, _: &Bounds, mut cb: T)
+    where
+        T: FnMut(CollisionObject<Id>),
+    {
+        for id in &self.ids {
+            cb(self.object(id));
+        }
+    }
+}
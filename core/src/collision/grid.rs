@@ -0,0 +1,237 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use model::game::Vector;
+use model::comp::Pos;
+use util::clamp;
+use super::{Bounds, Filter, CollisionObject, Broadphase, assert_in_world, assert_valid_dimensions};
+
+type SpatialHash = Vector;
+type Bucket<Id> = Vec<Id>;
+
+/// Inclusive range of grid cells covered by some bounds
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct CellRange {
+    min: SpatialHash,
+    max: SpatialHash,
+}
+impl CellRange {
+    fn contains(&self, cell: &SpatialHash) -> bool {
+        cell.x >= self.min.x && cell.x <= self.max.x && cell.y >= self.min.y &&
+            cell.y <= self.max.y
+    }
+    fn cells(&self) -> Vec<SpatialHash> {
+        let mut cells = Vec::new();
+        for y in self.min.y..self.max.y + 1 {
+            for x in self.min.x..self.max.x + 1 {
+                cells.push(SpatialHash { x, y });
+            }
+        }
+        cells
+    }
+}
+
+/// Uniform grid over the world. Entities are registered in every cell they touch,
+/// so they can have any size relative to the cells. Buckets are only allocated for occupied cells.
+pub struct Grid<Id> {
+    width: i32,
+    height: i32,
+    cell_size: i32,
+    entities: HashMap<Id, Bounds>,
+    filters: HashMap<Id, Filter>,
+    grid: HashMap<SpatialHash, Bucket<Id>>,
+}
+
+impl<Id> Grid<Id>
+where
+    Id: Hash + Eq + Clone,
+{
+    pub fn new(width: i32, height: i32, cell_size: i32) -> Self {
+        assert_valid_dimensions(width, height);
+        assert!(
+            cell_size > 0,
+            "Failed to create world: Cell size must be positive"
+        );
+        Grid {
+            width,
+            height,
+            cell_size,
+            entities: HashMap::new(),
+            filters: HashMap::new(),
+            grid: HashMap::new(),
+        }
+    }
+
+    fn cell_range(&self, bounds: &Bounds) -> CellRange {
+        // Bounds are centered, so odd sizes reach half a pixel further than integer division tells us
+        let left = bounds.x - bounds.width / 2 - 1;
+        let top = bounds.y - bounds.height / 2 - 1;
+        let right = bounds.x + bounds.width / 2 + 1;
+        let bottom = bounds.y + bounds.height / 2 + 1;
+        CellRange {
+            min: self.hash_point(left, top),
+            max: self.hash_point(right, bottom),
+        }
+    }
+
+    fn hash_point(&self, x: i32, y: i32) -> SpatialHash {
+        // Everything outside of the world is stored in the cells at its edges
+        SpatialHash {
+            x: clamp(x, 0, self.width - 1) / self.cell_size,
+            y: clamp(y, 0, self.height - 1) / self.cell_size,
+        }
+    }
+
+    fn remove_from_bucket(&mut self, id: &Id, cell: &SpatialHash) {
+        let is_empty = {
+            let bucket = self.grid.get_mut(cell).expect(
+                "Didn't find bucket of registered entity",
+            );
+            let pos = bucket.iter().position(|x| *x == *id).expect(
+                "Didn't find id in bucket",
+            );
+            bucket.remove(pos);
+            bucket.is_empty()
+        };
+        if is_empty {
+            self.grid.remove(cell);
+        }
+    }
+}
+
+impl<Id> Broadphase<Id> for Grid<Id>
+where
+    Id: Hash + Eq + Clone,
+{
+    fn width(&self) -> i32 {
+        self.width
+    }
+
+    fn height(&self) -> i32 {
+        self.height
+    }
+
+    fn get(&self, id: &Id) -> Option<&Bounds> {
+        self.entities.get(id)
+    }
+
+    fn get_filter(&self, id: &Id) -> Option<&Filter> {
+        self.filters.get(id)
+    }
+
+    fn add_filtered(&mut self, id: Id, bounds: Bounds, filter: Filter) {
+        assert_in_world(self.width, self.height, &bounds);
+        let cells = self.cell_range(&bounds).cells();
+        let old = self.entities.insert(id.clone(), bounds);
+        assert!(
+            old.is_none(),
+            "Failed to add new entity: Id already registered"
+        );
+        self.filters.insert(id.clone(), filter);
+        for cell in cells {
+            self.grid.entry(cell).or_insert_with(Bucket::new).push(
+                id.clone(),
+            );
+        }
+    }
+
+    fn place(&mut self, id: &Id, pos: &Pos) {
+        let mut bounds = self.entities
+            .get(id)
+            .expect("Failed to place entity: Id doesn't exist")
+            .clone();
+        let old_cells = self.cell_range(&bounds);
+
+        // Create new bounds
+        bounds.x = pos.x;
+        bounds.y = pos.y;
+        assert_in_world(self.width, self.height, &bounds);
+
+        let new_cells = self.cell_range(&bounds);
+        self.entities.insert(id.clone(), bounds);
+
+        if old_cells != new_cells {
+            for cell in old_cells.cells() {
+                if !new_cells.contains(&cell) {
+                    self.remove_from_bucket(id, &cell);
+                }
+            }
+            for cell in new_cells.cells() {
+                if !old_cells.contains(&cell) {
+                    self.grid.entry(cell).or_insert_with(Bucket::new).push(
+                        id.clone(),
+                    );
+                }
+            }
+        }
+    }
+
+    fn remove(&mut self, id: &Id) -> Option<Bounds> {
+        match self.entities.remove(id) {
+            Some(bounds) => {
+                self.filters.remove(id);
+                for cell in self.cell_range(&bounds).cells() {
+                    self.remove_from_bucket(id, &cell);
+                }
+                Some(bounds)
+            }
+            None => None,
+        }
+    }
+
+    fn query_intersects<T>(&self, mut cb: T)
+    where
+        T: FnMut(CollisionObject<Id>, CollisionObject<Id>),
+    {
+        for (cell, bucket) in &self.grid {
+            for (i, id) in bucket.iter().enumerate() {
+                let bounds = &self.entities[id];
+                let filter = &self.filters[id];
+                let cells = self.cell_range(bounds);
+                for other_id in &bucket[i + 1..] {
+                    let other_bounds = &self.entities[other_id];
+                    let other_filter = &self.filters[other_id];
+                    if !filter.interacts_with(other_filter) || !bounds.intersects(other_bounds) {
+                        continue;
+                    }
+                    // Entities sharing multiple cells are only reported in the first one
+                    let other_cells = self.cell_range(other_bounds);
+                    let first_shared_cell = SpatialHash {
+                        x: cells.min.x.max(other_cells.min.x),
+                        y: cells.min.y.max(other_cells.min.y),
+                    };
+                    if first_shared_cell == *cell {
+                        cb(
+                            CollisionObject { id, bounds, filter },
+                            CollisionObject {
+                                id: other_id,
+                                bounds: other_bounds,
+                                filter: other_filter,
+                            },
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn query_candidates<T>(&self, bounds: &Bounds, mut cb: T)
+    where
+        T: FnMut(CollisionObject<Id>),
+    {
+        let cells = self.cell_range(bounds).cells();
+        let mut seen = HashSet::new();
+        for cell in &cells {
+            if let Some(bucket) = self.grid.get(cell) {
+                for id in bucket {
+                    // With a single cell, no entity can be found twice
+                    if cells.len() > 1 && !seen.insert(id) {
+                        continue;
+                    }
+                    let bounds = &self.entities[id];
+                    let filter = &self.filters[id];
+                    cb(CollisionObject { id, bounds, filter })
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,2222 @@
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::f64::{INFINITY, NEG_INFINITY};
+use model::game::Vector;
+use model::comp::Pos;
+
+pub use self::grid::Grid;
+pub use self::quadtree::QuadTree;
+pub use self::sweep_and_prune::SweepAndPrune;
+pub use self::brute_force::BruteForce;
+
+mod grid;
+mod quadtree;
+mod sweep_and_prune;
+mod brute_force;
+
+/// Broadphase used by the game
+pub type World<Id> = Grid<Id>;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bounds {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+impl Bounds {
+    pub fn intersects(&self, other: &Self) -> bool {
+        ((self.x - other.x).abs() * 2 < (self.width + other.width)) &&
+            ((self.y - other.y).abs() * 2 < (self.height + other.height))
+    }
+    pub fn contains(&self, other: &Self) -> bool {
+        other.x - other.width / 2 >= self.x - self.width / 2 &&
+            other.y - other.height / 2 >= self.y - self.height / 2 &&
+            other.x + other.width / 2 <= self.x + self.width / 2 &&
+            other.y + other.height / 2 <= self.y + self.height / 2
+    }
+
+    /// Bounds covering the whole area travelled when moving by `vel`
+    pub fn swept(&self, vel: &Vector) -> Self {
+        // The extra pixel makes up for the rounding of the halved velocity
+        Bounds {
+            x: self.x + vel.x / 2,
+            y: self.y + vel.y / 2,
+            width: self.width + vel.x.abs() + 1,
+            height: self.height + vel.y.abs() + 1,
+        }
+    }
+
+    /// Describes how `self` has to be moved to stop overlapping with `other`.
+    /// Returns `None` if they don't overlap.
+    pub fn manifold(&self, other: &Self) -> Option<Manifold> {
+        if !self.intersects(other) {
+            return None;
+        }
+        let d_x = other.x - self.x;
+        let d_y = other.y - self.y;
+        // Doubled in order to stay exact with odd sizes
+        let double_penetration_x = self.width + other.width - d_x.abs() * 2;
+        let double_penetration_y = self.height + other.height - d_y.abs() * 2;
+        let manifold = if double_penetration_x < double_penetration_y {
+            Manifold {
+                normal: Vector {
+                    x: if d_x > 0 { -1 } else { 1 },
+                    y: 0,
+                },
+                depth: (double_penetration_x + 1) / 2,
+            }
+        } else {
+            Manifold {
+                normal: Vector {
+                    x: 0,
+                    y: if d_y > 0 { -1 } else { 1 },
+                },
+                depth: (double_penetration_y + 1) / 2,
+            }
+        };
+        Some(manifold)
+    }
+
+    /// Moves `self` by `vel` and reports when and where it first touches `other`.
+    /// Returns `None` if they don't meet during the movement or if `self` is moving away from `other`.
+    pub fn sweep(&self, vel: &Vector, other: &Self) -> Option<Hit> {
+        let hit = if let Some(manifold) = self.manifold(other) {
+            Hit {
+                time: 0.0,
+                normal: manifold.normal,
+            }
+        } else {
+            let reach_x = (self.width + other.width) as f64 / 2.0;
+            let reach_y = (self.height + other.height) as f64 / 2.0;
+            let d_x = (other.x - self.x) as f64;
+            let d_y = (other.y - self.y) as f64;
+            let (entry_x, exit_x) = axis_overlap_times(d_x, reach_x, vel.x as f64);
+            let (entry_y, exit_y) = axis_overlap_times(d_y, reach_y, vel.y as f64);
+            let entry = entry_x.max(entry_y);
+            let exit = exit_x.min(exit_y);
+            if entry >= exit || entry < 0.0 || entry > 1.0 {
+                return None;
+            }
+            let normal = if entry_x > entry_y {
+                Vector {
+                    x: -vel.x.signum(),
+                    y: 0,
+                }
+            } else {
+                Vector {
+                    x: 0,
+                    y: -vel.y.signum(),
+                }
+            };
+            Hit { time: entry, normal }
+        };
+
+        let is_approaching = vel.x * hit.normal.x + vel.y * hit.normal.y < 0;
+        if is_approaching { Some(hit) } else { None }
+    }
+}
+
+/// Time span in which two bodies overlap on a single axis,
+/// with `distance` measured from the moving body to the static one
+fn axis_overlap_times(distance: f64, reach: f64, speed: f64) -> (f64, f64) {
+    if speed == 0.0 {
+        if distance.abs() < reach {
+            (NEG_INFINITY, INFINITY)
+        } else {
+            (INFINITY, NEG_INFINITY)
+        }
+    } else {
+        let a = (distance - reach) / speed;
+        let b = (distance + reach) / speed;
+        (a.min(b), a.max(b))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Manifold {
+    /// Unit vector along which the overlap is resolved the quickest
+    pub normal: Vector,
+    /// Distance to move along the normal until the bodies only touch
+    pub depth: i32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RayHit {
+    /// Distance travelled from the start of the ray
+    pub distance: f64,
+    /// Unit vector pointing away from the hit face
+    pub normal: Vector,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hit {
+    /// Fraction of the movement after which the bodies touch, between 0 and 1
+    pub time: f64,
+    /// Unit vector pointing away from the touched face
+    pub normal: Vector,
+}
+
+pub type Layer = u32;
+
+pub mod layer {
+    use super::Layer;
+
+    pub const NONE: Layer = 0;
+    pub const PLAYER: Layer = 1;
+    pub const BALL: Layer = 1 << 1;
+    pub const WALL: Layer = 1 << 2;
+    pub const GOAL: Layer = 1 << 3;
+    pub const PICKUP: Layer = 1 << 4;
+    pub const SPECTATOR: Layer = 1 << 5;
+    pub const PROJECTILE: Layer = 1 << 6;
+    pub const ALL: Layer = !0;
+}
+
+/// Decides which entries get reported together by the queries of a `World`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Filter {
+    /// Layers the entry belongs to
+    pub layer: Layer,
+    /// Layers the entry wants to be reported together with
+    pub mask: Layer,
+    /// Triggers are reported when overlapping, but never get collided with
+    pub is_trigger: bool,
+}
+impl Filter {
+    pub fn new(layer: Layer, mask: Layer) -> Self {
+        Filter {
+            layer,
+            mask,
+            is_trigger: false,
+        }
+    }
+    pub fn trigger(layer: Layer, mask: Layer) -> Self {
+        Filter {
+            layer,
+            mask,
+            is_trigger: true,
+        }
+    }
+    pub fn interacts_with(&self, other: &Self) -> bool {
+        self.layer & other.mask != 0 && other.layer & self.mask != 0
+    }
+    pub fn is_solid_with(&self, other: &Self) -> bool {
+        !self.is_trigger && !other.is_trigger && self.interacts_with(other)
+    }
+}
+impl Default for Filter {
+    fn default() -> Self {
+        Filter::new(layer::ALL, layer::ALL)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CollisionObject<'a, Id: 'a> {
+    pub id: &'a Id,
+    pub bounds: &'a Bounds,
+    pub filter: &'a Filter,
+}
+
+/// Spatial index answering which entities of the world touch each other
+pub trait Broadphase<Id>
+where
+    Id: Hash + Eq + Clone,
+{
+    fn width(&self) -> i32;
+    fn height(&self) -> i32;
+    fn get(&self, id: &Id) -> Option<&Bounds>;
+    fn get_filter(&self, id: &Id) -> Option<&Filter>;
+    fn add_filtered(&mut self, id: Id, bounds: Bounds, filter: Filter);
+    fn place(&mut self, id: &Id, pos: &Pos);
+    fn remove(&mut self, id: &Id) -> Option<Bounds>;
+
+    /// Reports every pair of intersecting entities exactly once
+    fn query_intersects<T>(&self, cb: T)
+    where
+        T: FnMut(CollisionObject<Id>, CollisionObject<Id>);
+
+    /// Reports every entity that might intersect the given bounds, each at most once
+    fn query_candidates<T>(&self, bounds: &Bounds, cb: T)
+    where
+        T: FnMut(CollisionObject<Id>);
+
+    fn add(&mut self, id: Id, bounds: Bounds) {
+        self.add_filtered(id, bounds, Filter::default());
+    }
+
+    fn query_intersects_other<T>(&self, bounds: &Bounds, mut cb: T)
+    where
+        T: FnMut(CollisionObject<Id>),
+    {
+        self.query_candidates(bounds, |other| if bounds.intersects(other.bounds) {
+            cb(other);
+        });
+    }
+
+    fn query_intersects_id<T>(&self, id: &Id, mut cb: T)
+    where
+        T: FnMut(CollisionObject<Id>),
+    {
+        let bounds = self.get(id).expect(
+            "Failed to query for id: Id not registered",
+        );
+        let filter = self.get_filter(id).unwrap();
+        self.query_candidates(bounds, |other| if *id != *other.id &&
+            filter.interacts_with(other.filter) &&
+            bounds.intersects(other.bounds)
+        {
+            cb(other);
+        });
+    }
+
+    fn query_contains_other<T>(&self, bounds: &Bounds, mut cb: T)
+    where
+        T: FnMut(CollisionObject<Id>),
+    {
+        self.query_candidates(bounds, |other| if other.bounds.contains(bounds) {
+            cb(other);
+        });
+    }
+
+    /// Reports every solid entity overlapping the entity with the given id,
+    /// together with the way out of the overlap
+    fn query_manifold_id<T>(&self, id: &Id, mut cb: T)
+    where
+        T: FnMut(CollisionObject<Id>, Manifold),
+    {
+        let bounds = self.get(id).expect(
+            "Failed to query for id: Id not registered",
+        );
+        let filter = self.get_filter(id).unwrap();
+        self.query_candidates(bounds, |other| if *id != *other.id &&
+            filter.is_solid_with(other.filter)
+        {
+            if let Some(manifold) = bounds.manifold(other.bounds) {
+                cb(other, manifold);
+            }
+        });
+    }
+
+    /// Reports every solid entity the entity with the given id runs into when moving by `vel`
+    fn query_sweep_id<T>(&self, id: &Id, vel: &Vector, mut cb: T)
+    where
+        T: FnMut(CollisionObject<Id>, Hit),
+    {
+        let bounds = self.get(id).expect(
+            "Failed to sweep id: Id not registered",
+        );
+        let filter = self.get_filter(id).unwrap();
+        let swept = bounds.swept(vel);
+        self.query_candidates(&swept, |other| if *id != *other.id &&
+            filter.is_solid_with(other.filter) &&
+            swept.intersects(other.bounds)
+        {
+            if let Some(hit) = bounds.sweep(vel, other.bounds) {
+                cb(other, hit);
+            }
+        });
+    }
+
+    /// Finds the first solid entity the entity with the given id runs into when moving by `vel`
+    fn sweep_id(&self, id: &Id, vel: &Vector) -> Option<(Id, Hit)> {
+        let mut first: Option<(Id, Hit)> = None;
+        self.query_sweep_id(id, vel, |other, hit| {
+            let is_earlier = match first {
+                Some((_, ref first_hit)) => hit.time < first_hit.time,
+                None => true,
+            };
+            if is_earlier {
+                first = Some((other.id.clone(), hit));
+            }
+        });
+        first
+    }
+
+    /// Reports every solid entity on one of the given layers crossed by the segment between `from` and `to`.
+    /// Only the part of the segment inside of the world is considered.
+    /// Entities containing `from` are not reported.
+    fn query_segment<T>(&self, from: &Vector, to: &Vector, mask: Layer, mut cb: T)
+    where
+        T: FnMut(CollisionObject<Id>, RayHit),
+    {
+        let delta = Vector {
+            x: to.x - from.x,
+            y: to.y - from.y,
+        };
+        let (enter, exit) = match clip_to_world(from, &delta, self.width(), self.height()) {
+            Some(fractions) => fractions,
+            None => return,
+        };
+        let length = (delta.x as f64).hypot(delta.y as f64);
+        // A ray is nothing more than a point being swept
+        let point = Bounds {
+            x: from.x,
+            y: from.y,
+            width: 0,
+            height: 0,
+        };
+        self.query_candidates(&point.swept(&delta), |other| {
+            // Sweeping a point from inside of a box would report a hit right at the start
+            if other.filter.is_trigger || other.filter.layer & mask == 0 ||
+                point.intersects(other.bounds)
+            {
+                return;
+            }
+            if let Some(hit) = point.sweep(&delta, other.bounds) {
+                if hit.time >= enter && hit.time <= exit {
+                    let ray_hit = RayHit {
+                        distance: hit.time * length,
+                        normal: hit.normal,
+                    };
+                    cb(other, ray_hit);
+                }
+            }
+        });
+    }
+
+    /// All hits of `query_segment`, closest first
+    fn segment_cast_all(&self, from: &Vector, to: &Vector, mask: Layer) -> Vec<(Id, RayHit)> {
+        let mut hits = Vec::new();
+        self.query_segment(from, to, mask, |other, hit| {
+            hits.push((other.id.clone(), hit))
+        });
+        hits.sort_by(|&(_, ref a), &(_, ref b)| {
+            a.distance.partial_cmp(&b.distance).unwrap()
+        });
+        hits
+    }
+
+    /// Closest hit of `query_segment`
+    fn segment_cast(&self, from: &Vector, to: &Vector, mask: Layer) -> Option<(Id, RayHit)> {
+        let mut closest: Option<(Id, RayHit)> = None;
+        self.query_segment(from, to, mask, |other, hit| {
+            let is_closer = match closest {
+                Some((_, ref closest_hit)) => hit.distance < closest_hit.distance,
+                None => true,
+            };
+            if is_closer {
+                closest = Some((other.id.clone(), hit));
+            }
+        });
+        closest
+    }
+
+    /// Closest hit of a ray starting at `origin` and going towards `direction` until it leaves the world
+    fn raycast(&self, origin: &Vector, direction: &Vector, mask: Layer) -> Option<(Id, RayHit)> {
+        let longest_axis = direction.x.abs().max(direction.y.abs());
+        if longest_axis == 0 {
+            return None;
+        }
+        // Long enough to leave the world from anywhere inside of it
+        let scale = (self.width() + self.height()) / longest_axis + 1;
+        let to = Vector {
+            x: origin.x + direction.x * scale,
+            y: origin.y + direction.y * scale,
+        };
+        self.segment_cast(origin, &to, mask)
+    }
+
+    fn has_line_of_sight(&self, from: &Vector, to: &Vector, mask: Layer) -> bool {
+        self.segment_cast(from, to, mask).is_none()
+    }
+}
+
+/// Fractions of the movement from `from` by `delta` that lie inside of the world
+fn clip_to_world(from: &Vector, delta: &Vector, width: i32, height: i32) -> Option<(f64, f64)> {
+    let mut enter: f64 = 0.0;
+    let mut exit: f64 = 1.0;
+    for &(start, change, max) in &[(from.x, delta.x, width), (from.y, delta.y, height)] {
+        let start = start as f64;
+        let change = change as f64;
+        let max = max as f64;
+        if change == 0.0 {
+            if start < 0.0 || start > max {
+                return None;
+            }
+        } else {
+            let a = -start / change;
+            let b = (max - start) / change;
+            enter = enter.max(a.min(b));
+            exit = exit.min(a.max(b));
+        }
+    }
+    if enter <= exit {
+        Some((enter, exit))
+    } else {
+        None
+    }
+}
+
+fn assert_valid_dimensions(width: i32, height: i32) {
+    assert!(
+        width > 0 && height > 0,
+        "Failed to create world: Dimensions must be positive"
+    );
+}
+
+fn assert_in_world(width: i32, height: i32, bounds: &Bounds) {
+    assert!(
+        bounds.x + bounds.width / 2 > 0 && bounds.y + bounds.height / 2 > 0 &&
+            bounds.x - bounds.width / 2 < width &&
+            bounds.y - bounds.height / 2 < height,
+        "Bounds are completely outside of the world"
+    );
+}
+
+/// Outermost pixels covered by some bounds.
+/// As bounds are centered, odd sizes reach half a pixel further than integer division tells us.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Extent {
+    left: i32,
+    top: i32,
+    right: i32,
+    bottom: i32,
+}
+impl Extent {
+    fn of(bounds: &Bounds) -> Self {
+        Extent {
+            left: bounds.x - (bounds.width + 1) / 2,
+            top: bounds.y - (bounds.height + 1) / 2,
+            right: bounds.x + (bounds.width + 1) / 2,
+            bottom: bounds.y + (bounds.height + 1) / 2,
+        }
+    }
+    fn overlaps(&self, other: &Self) -> bool {
+        self.left < other.right && other.left < self.right && self.top < other.bottom &&
+            other.top < self.bottom
+    }
+    fn contains(&self, other: &Self) -> bool {
+        other.left >= self.left && other.right <= self.right && other.top >= self.top &&
+            other.bottom <= self.bottom
+    }
+}
+
+fn report_if_intersecting<'a, Id, T>(a: CollisionObject<'a, Id>, b: CollisionObject<'a, Id>, cb: &mut T)
+where
+    T: FnMut(CollisionObject<Id>, CollisionObject<Id>),
+{
+    if a.filter.interacts_with(b.filter) && a.bounds.intersects(b.bounds) {
+        cb(a, b);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CollisionEvent<Id> {
+    Started(Id, Id),
+    Ended(Id, Id),
+}
+
+/// Remembers which entities touched during the last update
+/// in order to tell new collisions apart from ongoing ones
+pub struct ContactTracker<Id> {
+    contacts: HashSet<(Id, Id)>,
+}
+
+impl<Id> Default for ContactTracker<Id>
+where
+    Id: Hash + Eq,
+{
+    fn default() -> Self {
+        ContactTracker { contacts: HashSet::new() }
+    }
+}
+
+impl<Id> ContactTracker<Id>
+where
+    Id: Hash + Ord + Clone,
+{
+    pub fn update<B>(&mut self, world: &B) -> Vec<CollisionEvent<Id>>
+    where
+        B: Broadphase<Id>,
+    {
+        let mut contacts = HashSet::new();
+        world.query_intersects(|a, b| {
+            let pair = if a.id < b.id {
+                (a.id.clone(), b.id.clone())
+            } else {
+                (b.id.clone(), a.id.clone())
+            };
+            contacts.insert(pair);
+        });
+
+        let mut events = Vec::new();
+        for &(ref a, ref b) in contacts.difference(&self.contacts) {
+            events.push(CollisionEvent::Started(a.clone(), b.clone()));
+        }
+        for &(ref a, ref b) in self.contacts.difference(&contacts) {
+            events.push(CollisionEvent::Ended(a.clone(), b.clone()));
+        }
+        self.contacts = contacts;
+        events
+    }
+
+    pub fn is_touching(&self, a: &Id, b: &Id) -> bool {
+        let pair = if a < b {
+            (a.clone(), b.clone())
+        } else {
+            (b.clone(), a.clone())
+        };
+        self.contacts.contains(&pair)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn intersects() {
+        let a = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        let b = Bounds {
+            x: 5,
+            y: 5,
+            width: 10,
+            height: 10,
+        };
+        assert!(a.intersects(&b));
+    }
+
+    #[test]
+    fn doesnt_intersect() {
+        let a = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        let b = Bounds {
+            x: 50,
+            y: 50,
+            width: 10,
+            height: 10,
+        };
+        assert!(!a.intersects(&b));
+    }
+
+    #[test]
+    fn doesnt_intersect_edge() {
+        let a = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        let b = Bounds {
+            x: 15,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        assert!(!a.intersects(&b));
+    }
+
+    #[derive(Clone, Debug)]
+    pub struct CollisionObjectClone<Id> {
+        pub id: Id,
+        pub bounds: Bounds,
+    }
+
+    impl<'a, Id> From<CollisionObject<'a, Id>> for CollisionObjectClone<Id>
+    where
+        Id: Clone,
+    {
+        fn from(obj: CollisionObject<'a, Id>) -> Self {
+            CollisionObjectClone {
+                id: obj.id.clone(),
+                bounds: obj.bounds.clone(),
+            }
+        }
+    }
+
+    #[test]
+    fn contains() {
+        let a = Bounds {
+            x: 0,
+            y: 0,
+            width: 100,
+            height: 100,
+        };
+        let b = Bounds {
+            x: 2,
+            y: 2,
+            width: 10,
+            height: 10,
+        };
+        assert!(a.contains(&b));
+    }
+
+    #[test]
+    fn doesnt_contain() {
+        let a = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        let b = Bounds {
+            x: 50,
+            y: 50,
+            width: 10,
+            height: 10,
+        };
+        assert!(!a.contains(&b));
+    }
+
+    #[test]
+    fn doesnt_contain_when_intersecting() {
+        let a = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        let b = Bounds {
+            x: 5,
+            y: 5,
+            width: 10,
+            height: 10,
+        };
+        assert!(!a.contains(&b));
+    }
+
+
+    #[test]
+    fn contains_edge() {
+        let a = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        let b = Bounds {
+            x: 10,
+            y: 10,
+            width: 0,
+            height: 0,
+        };
+        assert!(!a.contains(&b));
+    }
+
+    #[test]
+    fn contains_self() {
+        let a = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        assert!(a.contains(&a));
+    }
+
+    #[test]
+    fn init() {
+        World::<i32>::new(1000, 1000, 100);
+    }
+
+    #[test]
+    fn add() {
+        let mut world = World::new(1000, 1000, 100);
+        let bounds = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        world.add(1, bounds);
+    }
+
+    #[test]
+    #[should_panic]
+    fn add_too_low() {
+        let mut world = World::new(1000, 1000, 100);
+        let bounds = Bounds {
+            x: -10,
+            y: -10,
+            width: 1,
+            height: 1,
+        };
+        world.add(1, bounds);
+    }
+
+    #[test]
+    #[should_panic]
+    fn add_too_low_edge() {
+        let mut world = World::new(1000, 1000, 100);
+        let bounds = Bounds {
+            x: -10,
+            y: 0,
+            width: 5,
+            height: 5,
+        };
+        world.add(1, bounds);
+    }
+
+
+    #[test]
+    #[should_panic]
+    fn add_too_high() {
+        let mut world = World::new(1000, 1000, 100);
+        let bounds = Bounds {
+            x: 2000,
+            y: 2000,
+            width: 1,
+            height: 1,
+        };
+        world.add(1, bounds);
+    }
+
+
+    #[test]
+    #[should_panic]
+    fn add_too_high_edge() {
+        let mut world = World::new(1000, 1000, 100);
+        let bounds = Bounds {
+            x: 0,
+            y: 1010,
+            width: 1,
+            height: 5,
+        };
+        world.add(1, bounds);
+    }
+
+    #[test]
+    #[should_panic]
+    fn add_existing() {
+        let mut world = World::new(1000, 1000, 100);
+        let id = 1;
+        let bounds_a = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        world.add(id, bounds_a.clone());
+        let bounds_b = Bounds {
+            x: 1,
+            y: 1,
+            width: 10,
+            height: 10,
+        };
+        world.add(id, bounds_b);
+    }
+
+
+    #[test]
+    fn place() {
+        let mut world = World::new(1000, 1000, 100);
+        let id = 1;
+        let bounds = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        world.add(id, bounds.clone());
+        world.place(&id, &Vector { x: 30, y: 50 }.into());
+    }
+
+
+    #[test]
+    #[should_panic]
+    fn place_too_low() {
+        let mut world = World::new(1000, 1000, 100);
+        let id = 1;
+        let bounds = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        world.add(id, bounds.clone());
+        world.place(&id, &Vector { x: -999, y: 50 }.into());
+    }
+
+
+    #[test]
+    #[should_panic]
+    fn place_deleted() {
+        let mut world = World::new(1000, 1000, 100);
+        let id = 1;
+        let bounds = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        world.add(id, bounds.clone());
+        world.remove(&id);
+        world.place(&id, &Vector { x: 30, y: 50 }.into());
+    }
+
+
+    #[test]
+    #[should_panic]
+    fn place_nonexistant() {
+        let mut world = World::new(1000, 1000, 100);
+        let id = 1;
+        let bounds = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        world.add(id, bounds.clone());
+        world.place(&(id + 1), &Vector { x: 30, y: 50 }.into());
+    }
+
+
+    #[test]
+    fn remove() {
+        let mut world = World::new(1000, 1000, 100);
+        let id = 1;
+        let bounds = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        world.add(id, bounds.clone());
+        world.remove(&id);
+        world.add(id, bounds)
+    }
+
+    #[test]
+    fn remove_nonexistant() {
+        let mut world = World::new(1000, 1000, 100);
+        let bounds = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        world.add(1, bounds);
+        let removed = world.remove(&2);
+        assert!(removed.is_none())
+    }
+
+    #[test]
+    fn remove_empty() {
+        let mut world = World::new(1000, 1000, 100);
+        let removed = world.remove(&1);
+        assert!(removed.is_none())
+    }
+
+    #[test]
+    fn remove_twice() {
+        let mut world = World::new(1000, 1000, 100);
+        let id = 1;
+        let bounds = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        world.add(id, bounds.clone());
+        let removed = world.remove(&id);
+        assert_eq!(bounds, removed.unwrap());
+        let removed = world.remove(&id);
+        assert!(removed.is_none());
+    }
+
+    #[test]
+    fn no_collisions() {
+        let mut world = World::new(1000, 1000, 100);
+        world.add(
+            1,
+            Bounds {
+                x: 0,
+                y: 0,
+                width: 10,
+                height: 10,
+            },
+        );
+        world.add(
+            2,
+            Bounds {
+                x: 40,
+                y: 40,
+                width: 10,
+                height: 10,
+            },
+        );
+        world.query_intersects(|_, _| panic!());
+    }
+
+    #[test]
+    fn one_collision() {
+        let mut world = World::new(1000, 1000, 100);
+        let id_a = 1;
+        let bounds_a = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        world.add(id_a, bounds_a.clone());
+        let id_b = 2;
+        let bounds_b = Bounds {
+            x: 5,
+            y: 5,
+            width: 10,
+            height: 10,
+        };
+        world.add(id_b, bounds_b.clone());
+
+        let mut collisions = Vec::<(CollisionObjectClone<i32>, CollisionObjectClone<i32>)>::new();
+        world.query_intersects(|a, b| collisions.push((a.into(), b.into())));
+
+        assert_eq!(1, collisions.len());
+        let &(ref a, ref b) = collisions.first().unwrap();
+        assert_eq!(id_a, a.id);
+        assert_eq!(id_b, b.id);
+        assert_eq!(bounds_a, a.bounds);
+        assert_eq!(bounds_b, b.bounds);
+    }
+
+
+    #[test]
+    fn multiple_collisions() {
+        let mut world = World::new(1000, 1000, 100);
+        let id_a = 1;
+        let bounds_a = Bounds {
+            x: 3,
+            y: 5,
+            width: 10,
+            height: 70,
+        };
+        world.add(id_a, bounds_a.clone());
+        let not_containing = Bounds {
+            x: 54,
+            y: 60,
+            width: 3,
+            height: 6,
+        };
+        world.add(2, not_containing);
+        let id_b = 3;
+        let bounds_b = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        world.add(id_b, bounds_b.clone());
+        let id_c = 4;
+        let bounds_c = Bounds {
+            x: 5,
+            y: 5,
+            width: 10,
+            height: 10,
+        };
+        world.add(id_c, bounds_c.clone());
+        let mut collisions = Vec::<(CollisionObjectClone<i32>, CollisionObjectClone<i32>)>::new();
+        world.query_intersects(|a, b| collisions.push((a.into(), b.into())));
+        assert_eq!(3, collisions.len());
+        let a_b_pos = collisions
+            .iter()
+            .position(|&(ref lhs, ref rhs)| {
+                (lhs.id == id_a && lhs.bounds == bounds_a && rhs.id == id_b &&
+                     rhs.bounds == bounds_b) ||
+                    (rhs.id == id_a && rhs.bounds == bounds_a && lhs.id == id_b &&
+                         lhs.bounds == bounds_b)
+            })
+            .unwrap();
+        let a_c_pos = collisions
+            .iter()
+            .position(|&(ref lhs, ref rhs)| {
+                (lhs.id == id_a && lhs.bounds == bounds_a && rhs.id == id_c &&
+                     rhs.bounds == bounds_c) ||
+                    (rhs.id == id_a && rhs.bounds == bounds_a && lhs.id == id_c &&
+                         lhs.bounds == bounds_c)
+            })
+            .unwrap();
+        let b_c_pos = collisions
+            .iter()
+            .position(|&(ref lhs, ref rhs)| {
+                (lhs.id == id_b && lhs.bounds == bounds_b && rhs.id == id_c &&
+                     rhs.bounds == bounds_c) ||
+                    (rhs.id == id_b && rhs.bounds == bounds_b && lhs.id == id_c &&
+                         lhs.bounds == bounds_c)
+            })
+            .unwrap();
+        assert!(a_b_pos != a_c_pos && a_b_pos != b_c_pos && a_c_pos != b_c_pos);
+    }
+
+
+    #[test]
+    fn no_collisions_id() {
+        let mut world = World::new(1000, 1000, 100);
+        world.add(
+            1,
+            Bounds {
+                x: 0,
+                y: 0,
+                width: 10,
+                height: 10,
+            },
+        );
+        world.add(
+            2,
+            Bounds {
+                x: 40,
+                y: 40,
+                width: 10,
+                height: 10,
+            },
+        );
+        world.query_intersects_id(&1, |_| panic!());
+    }
+
+    #[test]
+    fn one_collision_id() {
+        let mut world = World::new(1000, 1000, 100);
+        let id_a = 1;
+        let bounds_a = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        world.add(id_a, bounds_a.clone());
+        let id_b = 2;
+        let bounds_b = Bounds {
+            x: 5,
+            y: 5,
+            width: 10,
+            height: 10,
+        };
+        world.add(id_b, bounds_b.clone());
+
+        let mut collisions = Vec::<CollisionObjectClone<i32>>::new();
+        world.query_intersects_id(&id_a, |b| collisions.push(b.into()));
+
+        assert_eq!(1, collisions.len());
+        let b = collisions.first().unwrap();
+        assert_eq!(id_b, b.id);
+        assert_eq!(bounds_b, b.bounds);
+    }
+
+
+    #[test]
+    fn multiple_collisions_id() {
+        let mut world = World::new(1000, 1000, 100);
+        let id_a = 1;
+        let bounds_a = Bounds {
+            x: 3,
+            y: 5,
+            width: 10,
+            height: 70,
+        };
+        world.add(id_a, bounds_a.clone());
+        let not_containing = Bounds {
+            x: 54,
+            y: 60,
+            width: 3,
+            height: 6,
+        };
+        world.add(2, not_containing);
+        let id_b = 3;
+        let bounds_b = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        world.add(id_b, bounds_b.clone());
+        let id_c = 4;
+        let bounds_c = Bounds {
+            x: 5,
+            y: 5,
+            width: 10,
+            height: 10,
+        };
+        world.add(id_c, bounds_c.clone());
+        let mut collisions = Vec::<CollisionObjectClone<i32>>::new();
+        world.query_intersects_id(&id_c, |b| collisions.push(b.into()));
+        assert_eq!(2, collisions.len());
+        let a_c_pos = collisions
+            .iter()
+            .position(|a| a.id == id_a && a.bounds == bounds_a)
+            .unwrap();
+        let b_c_pos = collisions
+            .iter()
+            .position(|b| b.id == id_b && b.bounds == bounds_b)
+            .unwrap();
+        assert!(a_c_pos != b_c_pos);
+    }
+
+
+    #[test]
+    fn no_collisions_other() {
+        let mut world = World::new(1000, 1000, 100);
+        let id = 1;
+        let bounds_a = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        world.add(id, bounds_a.clone());
+        let bounds_b = Bounds {
+            x: 40,
+            y: 40,
+            width: 10,
+            height: 10,
+        };
+        world.query_intersects_other(&bounds_b, |_| panic!());
+    }
+
+    #[test]
+    fn one_collision_other() {
+        let mut world = World::new(1000, 1000, 100);
+        let id = 1;
+        let bounds_a = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        world.add(id, bounds_a.clone());
+        let bounds_b = Bounds {
+            x: 5,
+            y: 5,
+            width: 10,
+            height: 10,
+        };
+        let mut collisions = Vec::new();
+        world.query_intersects_other(&bounds_b, |obj| {
+            collisions.push((*obj.id, obj.bounds.clone()))
+        });
+        assert_eq!(1, collisions.len());
+        let &(coll_id, ref coll_bounds) = collisions.first().unwrap();
+        assert_eq!(id, coll_id);
+        assert_eq!(bounds_a, *coll_bounds);
+    }
+
+
+    #[test]
+    fn multiple_collision_other() {
+        let mut world = World::new(1000, 1000, 100);
+        let id_a = 1;
+        let bounds_a = Bounds {
+            x: 3,
+            y: 5,
+            width: 10,
+            height: 70,
+        };
+        world.add(id_a, bounds_a.clone());
+        let not_containing = Bounds {
+            x: 54,
+            y: 60,
+            width: 3,
+            height: 6,
+        };
+        world.add(2, not_containing);
+        let id_b = 3;
+        let bounds_b = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        world.add(id_b, bounds_b.clone());
+        let bounds_c = Bounds {
+            x: 5,
+            y: 5,
+            width: 10,
+            height: 10,
+        };
+        let mut collisions = Vec::new();
+        world.query_intersects_other(&bounds_c, |obj| {
+            collisions.push((*obj.id, obj.bounds.clone()))
+        });
+        assert_eq!(2, collisions.len());
+        let (coll_id, ref coll_bounds) = collisions[0];
+        assert_eq!(id_a, coll_id);
+        assert_eq!(bounds_a, *coll_bounds);
+
+        let (coll_id, ref coll_bounds) = collisions[1];
+        assert_eq!(id_b, coll_id);
+        assert_eq!(bounds_b, *coll_bounds);
+    }
+
+    #[test]
+    fn no_containing_other() {
+        let mut world = World::new(1000, 1000, 100);
+        let id = 1;
+        let bounds_a = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        world.add(id, bounds_a.clone());
+        let bounds_b = Bounds {
+            x: 40,
+            y: 40,
+            width: 10,
+            height: 10,
+        };
+        world.query_contains_other(&bounds_b, |_| panic!());
+    }
+
+    #[test]
+    fn one_containing_other() {
+        let mut world = World::new(1000, 1000, 100);
+        let id = 1;
+        let bounds_a = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        world.add(id, bounds_a.clone());
+        let bounds_b = Bounds {
+            x: 2,
+            y: 2,
+            width: 3,
+            height: 3,
+        };
+        let mut containing = Vec::new();
+        world.query_contains_other(&bounds_b, |obj| {
+            containing.push((*obj.id, obj.bounds.clone()))
+        });
+        assert_eq!(1, containing.len());
+        let &(coll_id, ref coll_bounds) = containing.first().unwrap();
+        assert_eq!(id, coll_id);
+        assert_eq!(bounds_a, *coll_bounds);
+    }
+
+
+    #[test]
+    fn multiple_containing_other() {
+        let mut world = World::new(1000, 1000, 100);
+        let id_a = 1;
+        let bounds_a = Bounds {
+            x: 4,
+            y: 4,
+            width: 100,
+            height: 100,
+        };
+        world.add(id_a, bounds_a.clone());
+        let not_containing = Bounds {
+            x: 0,
+            y: 0,
+            width: 3,
+            height: 6,
+        };
+        world.add(2, not_containing);
+        let id_b = 3;
+        let bounds_b = Bounds {
+            x: 6,
+            y: 6,
+            width: 2,
+            height: 2,
+        };
+        world.add(id_b, bounds_b.clone());
+        let bounds_c = Bounds {
+            x: 5,
+            y: 5,
+            width: 1,
+            height: 1,
+        };
+        let mut containing = Vec::new();
+        world.query_contains_other(&bounds_c, |obj| {
+            containing.push((*obj.id, obj.bounds.clone()))
+        });
+        assert_eq!(2, containing.len());
+        let (coll_id, ref coll_bounds) = containing[0];
+        assert_eq!(id_a, coll_id);
+        assert_eq!(bounds_a, *coll_bounds);
+
+        let (coll_id, ref coll_bounds) = containing[1];
+        assert_eq!(id_b, coll_id);
+        assert_eq!(bounds_b, *coll_bounds);
+    }
+
+
+    #[test]
+    fn sweep_hit() {
+        let a = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        let b = Bounds {
+            x: 30,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        let hit = a.sweep(&Vector { x: 40, y: 0 }, &b).unwrap();
+        assert_eq!(0.5, hit.time);
+        assert_eq!(Vector { x: -1, y: 0 }, hit.normal);
+    }
+
+    #[test]
+    fn sweep_hit_vertical() {
+        let a = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        let b = Bounds {
+            x: 2,
+            y: -25,
+            width: 10,
+            height: 10,
+        };
+        let hit = a.sweep(&Vector { x: 0, y: -20 }, &b).unwrap();
+        assert_eq!(0.75, hit.time);
+        assert_eq!(Vector { x: 0, y: 1 }, hit.normal);
+    }
+
+    #[test]
+    fn sweep_miss() {
+        let a = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        let b = Bounds {
+            x: 30,
+            y: 30,
+            width: 10,
+            height: 10,
+        };
+        assert!(a.sweep(&Vector { x: 40, y: 0 }, &b).is_none());
+    }
+
+    #[test]
+    fn sweep_too_short() {
+        let a = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        let b = Bounds {
+            x: 30,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        assert!(a.sweep(&Vector { x: 19, y: 0 }, &b).is_none());
+    }
+
+    #[test]
+    fn sweep_moving_away() {
+        let a = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        let b = Bounds {
+            x: 30,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        assert!(a.sweep(&Vector { x: -40, y: 0 }, &b).is_none());
+    }
+
+    #[test]
+    fn sweep_through_thin_wall() {
+        let ball = Bounds {
+            x: 100,
+            y: 500,
+            width: 15,
+            height: 15,
+        };
+        let paddle = Bounds {
+            x: 20,
+            y: 500,
+            width: 15,
+            height: 75,
+        };
+        let vel = Vector { x: -150, y: 10 };
+        let moved = Bounds {
+            x: ball.x + vel.x,
+            y: ball.y + vel.y,
+            ..ball.clone()
+        };
+        assert!(!moved.intersects(&paddle));
+
+        let hit = ball.sweep(&vel, &paddle).unwrap();
+        assert!(hit.time > 0.0 && hit.time < 1.0);
+        assert_eq!(Vector { x: 1, y: 0 }, hit.normal);
+    }
+
+    #[test]
+    fn sweep_overlapping() {
+        let a = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        let b = Bounds {
+            x: 8,
+            y: 2,
+            width: 10,
+            height: 10,
+        };
+        let hit = a.sweep(&Vector { x: 5, y: 0 }, &b).unwrap();
+        assert_eq!(0.0, hit.time);
+        assert_eq!(Vector { x: -1, y: 0 }, hit.normal);
+        assert!(a.sweep(&Vector { x: -5, y: 0 }, &b).is_none());
+    }
+
+    #[test]
+    fn sweep_id_first_hit() {
+        let mut world = World::new(1000, 1000, 100);
+        world.add(
+            1,
+            Bounds {
+                x: 20,
+                y: 50,
+                width: 10,
+                height: 10,
+            },
+        );
+        world.add(
+            2,
+            Bounds {
+                x: 60,
+                y: 50,
+                width: 10,
+                height: 10,
+            },
+        );
+        world.add(
+            3,
+            Bounds {
+                x: 45,
+                y: 50,
+                width: 10,
+                height: 10,
+            },
+        );
+        let (id, hit) = world.sweep_id(&1, &Vector { x: 50, y: 0 }).unwrap();
+        assert_eq!(3, id);
+        assert_eq!(0.3, hit.time);
+    }
+
+    #[test]
+    fn sweep_id_no_hit() {
+        let mut world = World::new(1000, 1000, 100);
+        world.add(
+            1,
+            Bounds {
+                x: 20,
+                y: 50,
+                width: 10,
+                height: 10,
+            },
+        );
+        world.add(
+            2,
+            Bounds {
+                x: 60,
+                y: 80,
+                width: 10,
+                height: 10,
+            },
+        );
+        assert!(world.sweep_id(&1, &Vector { x: 50, y: 0 }).is_none());
+    }
+
+    #[test]
+    fn manifold() {
+        let a = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        let b = Bounds {
+            x: 3,
+            y: 8,
+            width: 10,
+            height: 10,
+        };
+        let manifold = a.manifold(&b).unwrap();
+        assert_eq!(Vector { x: 0, y: -1 }, manifold.normal);
+        assert_eq!(2, manifold.depth);
+    }
+
+    #[test]
+    fn manifold_resolves_overlap() {
+        let a = Bounds {
+            x: 30,
+            y: 0,
+            width: 15,
+            height: 15,
+        };
+        let b = Bounds {
+            x: 20,
+            y: 3,
+            width: 15,
+            height: 75,
+        };
+        let manifold = a.manifold(&b).unwrap();
+        assert_eq!(Vector { x: 1, y: 0 }, manifold.normal);
+        let separated = Bounds {
+            x: a.x + manifold.normal.x * manifold.depth,
+            y: a.y + manifold.normal.y * manifold.depth,
+            ..a.clone()
+        };
+        assert!(!separated.intersects(&b));
+        let too_little = Bounds {
+            x: separated.x - manifold.normal.x,
+            y: separated.y - manifold.normal.y,
+            ..a.clone()
+        };
+        assert!(too_little.intersects(&b));
+    }
+
+    #[test]
+    fn no_manifold() {
+        let a = Bounds {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        let b = Bounds {
+            x: 10,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        assert!(a.manifold(&b).is_none());
+    }
+
+    #[test]
+    fn manifold_id() {
+        let mut world = World::new(1000, 1000, 100);
+        world.add(
+            1,
+            Bounds {
+                x: 50,
+                y: 50,
+                width: 10,
+                height: 10,
+            },
+        );
+        world.add(
+            2,
+            Bounds {
+                x: 56,
+                y: 51,
+                width: 10,
+                height: 10,
+            },
+        );
+        world.add(
+            3,
+            Bounds {
+                x: 80,
+                y: 80,
+                width: 10,
+                height: 10,
+            },
+        );
+        let mut manifolds = Vec::new();
+        world.query_manifold_id(&1, |other, manifold| manifolds.push((*other.id, manifold)));
+        assert_eq!(1, manifolds.len());
+        let (id, ref manifold) = manifolds[0];
+        assert_eq!(2, id);
+        assert_eq!(Vector { x: -1, y: 0 }, manifold.normal);
+        assert_eq!(4, manifold.depth);
+    }
+
+    #[test]
+    fn filter_interaction() {
+        let player = Filter::new(layer::PLAYER, layer::BALL);
+        let ball = Filter::new(layer::BALL, layer::PLAYER | layer::BALL);
+        let spectator = Filter::new(layer::SPECTATOR, layer::NONE);
+        assert!(player.interacts_with(&ball));
+        assert!(ball.interacts_with(&player));
+        assert!(ball.interacts_with(&ball));
+        assert!(!player.interacts_with(&player));
+        assert!(!spectator.interacts_with(&ball));
+        assert!(!ball.interacts_with(&spectator));
+    }
+
+    #[test]
+    fn filtered_collisions() {
+        let mut world = World::new(1000, 1000, 100);
+        let bounds = Bounds {
+            x: 50,
+            y: 50,
+            width: 10,
+            height: 10,
+        };
+        world.add_filtered(1, bounds.clone(), Filter::new(layer::BALL, layer::PLAYER));
+        world.add_filtered(2, bounds.clone(), Filter::new(layer::PLAYER, layer::BALL));
+        world.add_filtered(3, bounds.clone(), Filter::new(layer::PLAYER, layer::BALL));
+        world.add_filtered(4, bounds, Filter::new(layer::SPECTATOR, layer::NONE));
+
+        let mut collisions = Vec::new();
+        world.query_intersects(|a, b| collisions.push((*a.id, *b.id)));
+        assert_eq!(2, collisions.len());
+        assert!(collisions.contains(&(1, 2)));
+        assert!(collisions.contains(&(1, 3)));
+
+        let mut collisions = Vec::new();
+        world.query_intersects_id(&2, |other| collisions.push(*other.id));
+        assert_eq!(vec![1], collisions);
+
+        world.query_intersects_id(&4, |_| panic!());
+    }
+
+    #[test]
+    fn trigger_not_solid() {
+        let mut world = World::new(1000, 1000, 100);
+        world.add_filtered(
+            1,
+            Bounds {
+                x: 50,
+                y: 50,
+                width: 10,
+                height: 10,
+            },
+            Filter::new(layer::BALL, layer::ALL),
+        );
+        world.add_filtered(
+            2,
+            Bounds {
+                x: 55,
+                y: 50,
+                width: 10,
+                height: 10,
+            },
+            Filter::trigger(layer::GOAL, layer::BALL),
+        );
+        world.add_filtered(
+            3,
+            Bounds {
+                x: 90,
+                y: 50,
+                width: 10,
+                height: 10,
+            },
+            Filter::trigger(layer::GOAL, layer::BALL),
+        );
+
+        let mut triggered = Vec::new();
+        world.query_intersects_id(&1, |other| {
+            assert!(other.filter.is_trigger);
+            triggered.push(*other.id)
+        });
+        assert_eq!(vec![2], triggered);
+        world.query_manifold_id(&1, |_, _| panic!());
+        assert!(world.sweep_id(&1, &Vector { x: 50, y: 0 }).is_none());
+    }
+
+    #[test]
+    fn contact_tracking() {
+        let mut world = World::new(1000, 1000, 100);
+        let mut tracker = ContactTracker::default();
+        world.add(
+            1,
+            Bounds {
+                x: 50,
+                y: 50,
+                width: 10,
+                height: 10,
+            },
+        );
+        world.add(
+            2,
+            Bounds {
+                x: 80,
+                y: 50,
+                width: 10,
+                height: 10,
+            },
+        );
+        assert!(tracker.update(&world).is_empty());
+
+        world.place(&2, &Vector { x: 55, y: 50 }.into());
+        assert_eq!(vec![CollisionEvent::Started(1, 2)], tracker.update(&world));
+        assert!(tracker.is_touching(&2, &1));
+
+        world.place(&2, &Vector { x: 57, y: 50 }.into());
+        assert!(tracker.update(&world).is_empty());
+
+        world.place(&2, &Vector { x: 80, y: 50 }.into());
+        assert_eq!(vec![CollisionEvent::Ended(1, 2)], tracker.update(&world));
+        assert!(!tracker.is_touching(&1, &2));
+    }
+
+    #[test]
+    fn contact_ends_on_removal() {
+        let mut world = World::new(1000, 1000, 100);
+        let mut tracker = ContactTracker::default();
+        let bounds = Bounds {
+            x: 50,
+            y: 50,
+            width: 10,
+            height: 10,
+        };
+        world.add(1, bounds.clone());
+        world.add(2, bounds);
+        assert_eq!(vec![CollisionEvent::Started(1, 2)], tracker.update(&world));
+        world.remove(&1);
+        assert_eq!(vec![CollisionEvent::Ended(1, 2)], tracker.update(&world));
+    }
+
+    #[test]
+    fn custom_cell_size() {
+        let mut world = World::new(1000, 500, 7);
+        world.add(
+            1,
+            Bounds {
+                x: 3,
+                y: 3,
+                width: 4,
+                height: 4,
+            },
+        );
+        world.add(
+            2,
+            Bounds {
+                x: 6,
+                y: 5,
+                width: 4,
+                height: 4,
+            },
+        );
+        let mut collisions = Vec::new();
+        world.query_intersects(|a, b| collisions.push((*a.id, *b.id)));
+        assert_eq!(1, collisions.len());
+    }
+
+    #[test]
+    fn larger_than_cells() {
+        let mut world = World::new(1000, 1000, 10);
+        world.add(
+            1,
+            Bounds {
+                x: 500,
+                y: 500,
+                width: 900,
+                height: 30,
+            },
+        );
+        world.add(
+            2,
+            Bounds {
+                x: 90,
+                y: 510,
+                width: 5,
+                height: 5,
+            },
+        );
+        world.add(
+            3,
+            Bounds {
+                x: 900,
+                y: 490,
+                width: 5,
+                height: 5,
+            },
+        );
+        let mut collisions = Vec::new();
+        world.query_intersects(|a, b| collisions.push((*a.id, *b.id)));
+        assert_eq!(2, collisions.len());
+
+        let mut found = Vec::new();
+        world.query_intersects_id(&3, |other| found.push(*other.id));
+        assert_eq!(vec![1], found);
+
+        world.remove(&1);
+        world.query_intersects(|_, _| panic!());
+    }
+
+    #[test]
+    fn spanning_cell_edges() {
+        let mut world = World::new(1000, 1000, 100);
+        world.add(
+            1,
+            Bounds {
+                x: 95,
+                y: 50,
+                width: 10,
+                height: 10,
+            },
+        );
+        world.add(
+            2,
+            Bounds {
+                x: 104,
+                y: 50,
+                width: 10,
+                height: 10,
+            },
+        );
+        let mut collisions = Vec::new();
+        world.query_intersects(|a, b| collisions.push((*a.id, *b.id)));
+        assert_eq!(1, collisions.len());
+
+        world.place(&2, &Vector { x: 300, y: 50 }.into());
+        world.query_intersects(|_, _| panic!());
+        world.place(&2, &Vector { x: 101, y: 55 }.into());
+        let mut collisions = Vec::new();
+        world.query_intersects(|a, b| collisions.push((*a.id, *b.id)));
+        assert_eq!(1, collisions.len());
+    }
+
+    fn to_bounds(&(x, y, width, height): &(u16, u16, u8, u8), world_size: i32) -> Bounds {
+        // Always keep at least a pixel inside the world
+        Bounds {
+            x: 1 + i32::from(x) % (world_size - 2),
+            y: 1 + i32::from(y) % (world_size - 2),
+            width: i32::from(width),
+            height: i32::from(height),
+        }
+    }
+
+    fn to_world(cell_size: u8, raw_bounds: &[(u16, u16, u8, u8)]) -> (World<usize>, Vec<Bounds>) {
+        const WORLD_SIZE: i32 = 1000;
+        let mut world = World::new(WORLD_SIZE, WORLD_SIZE, 10 + i32::from(cell_size));
+        let mut all_bounds = Vec::new();
+        for (id, raw) in raw_bounds.iter().enumerate() {
+            let bounds = to_bounds(raw, WORLD_SIZE);
+            world.add(id, bounds.clone());
+            all_bounds.push(bounds);
+        }
+        (world, all_bounds)
+    }
+
+    fn brute_force_pairs(all_bounds: &[Bounds]) -> HashSet<(usize, usize)> {
+        let mut pairs = HashSet::new();
+        for (a, bounds_a) in all_bounds.iter().enumerate() {
+            for (b, bounds_b) in all_bounds.iter().enumerate().skip(a + 1) {
+                if bounds_a.intersects(bounds_b) {
+                    pairs.insert((a, b));
+                }
+            }
+        }
+        pairs
+    }
+
+    fn grid_pairs(world: &World<usize>) -> Option<HashSet<(usize, usize)>> {
+        let mut pairs = HashSet::new();
+        let mut is_unique = true;
+        world.query_intersects(|a, b| {
+            let pair = if a.id < b.id {
+                (*a.id, *b.id)
+            } else {
+                (*b.id, *a.id)
+            };
+            is_unique &= pairs.insert(pair);
+        });
+        if is_unique { Some(pairs) } else { None }
+    }
+
+    quickcheck! {
+        fn intersects_like_brute_force(cell_size: u8, raw_bounds: Vec<(u16, u16, u8, u8)>) -> bool {
+            let (world, all_bounds) = to_world(cell_size, &raw_bounds);
+            grid_pairs(&world) == Some(brute_force_pairs(&all_bounds))
+        }
+
+        fn intersects_like_brute_force_after_placing(
+            cell_size: u8,
+            raw_bounds: Vec<(u16, u16, u8, u8)>,
+            new_positions: Vec<(u16, u16)>
+        ) -> bool {
+            let (mut world, mut all_bounds) = to_world(cell_size, &raw_bounds);
+            for (id, &(x, y)) in new_positions.iter().enumerate().take(all_bounds.len()) {
+                let moved = to_bounds(&(x, y, 0, 0), 1000);
+                world.place(&id, &Vector { x: moved.x, y: moved.y }.into());
+                all_bounds[id].x = moved.x;
+                all_bounds[id].y = moved.y;
+            }
+            grid_pairs(&world) == Some(brute_force_pairs(&all_bounds))
+        }
+
+        fn intersects_other_like_brute_force(
+            cell_size: u8,
+            raw_bounds: Vec<(u16, u16, u8, u8)>,
+            raw_query: (u16, u16, u8, u8)
+        ) -> bool {
+            let (world, all_bounds) = to_world(cell_size, &raw_bounds);
+            let query = to_bounds(&raw_query, 1000);
+            let mut found = Vec::new();
+            world.query_intersects_other(&query, |other| found.push(*other.id));
+            found.sort();
+            let expected = all_bounds
+                .iter()
+                .enumerate()
+                .filter(|&(_, bounds)| bounds.intersects(&query))
+                .map(|(id, _)| id)
+                .collect::<Vec<_>>();
+            found == expected
+        }
+
+        fn quadtree_like_brute_force(
+            raw_bounds: Vec<(u16, u16, u8, u8)>,
+            new_positions: Vec<(u16, u16)>,
+            removals: Vec<u8>,
+            raw_query: (u16, u16, u8, u8)
+        ) -> bool {
+            behaves_like_brute_force(
+                QuadTree::new(1000, 1000),
+                &raw_bounds,
+                &new_positions,
+                &removals,
+                &raw_query,
+            )
+        }
+
+        fn sweep_and_prune_like_brute_force(
+            raw_bounds: Vec<(u16, u16, u8, u8)>,
+            new_positions: Vec<(u16, u16)>,
+            removals: Vec<u8>,
+            raw_query: (u16, u16, u8, u8)
+        ) -> bool {
+            behaves_like_brute_force(
+                SweepAndPrune::new(1000, 1000),
+                &raw_bounds,
+                &new_positions,
+                &removals,
+                &raw_query,
+            )
+        }
+
+        fn grid_like_brute_force(
+            cell_size: u8,
+            raw_bounds: Vec<(u16, u16, u8, u8)>,
+            new_positions: Vec<(u16, u16)>,
+            removals: Vec<u8>,
+            raw_query: (u16, u16, u8, u8)
+        ) -> bool {
+            behaves_like_brute_force(
+                Grid::new(1000, 1000, 10 + i32::from(cell_size)),
+                &raw_bounds,
+                &new_positions,
+                &removals,
+                &raw_query,
+            )
+        }
+    }
+
+    fn sorted_pairs<B>(world: &B) -> Vec<(usize, usize)>
+    where
+        B: Broadphase<usize>,
+    {
+        let mut pairs = Vec::new();
+        world.query_intersects(|a, b| if a.id < b.id {
+            pairs.push((*a.id, *b.id))
+        } else {
+            pairs.push((*b.id, *a.id))
+        });
+        pairs.sort();
+        pairs
+    }
+
+    fn sorted_intersecting<B>(world: &B, bounds: &Bounds) -> Vec<usize>
+    where
+        B: Broadphase<usize>,
+    {
+        let mut found = Vec::new();
+        world.query_intersects_other(bounds, |other| found.push(*other.id));
+        found.sort();
+        found
+    }
+
+    fn behaves_like_brute_force<B>(
+        mut world: B,
+        raw_bounds: &[(u16, u16, u8, u8)],
+        new_positions: &[(u16, u16)],
+        removals: &[u8],
+        raw_query: &(u16, u16, u8, u8),
+    ) -> bool
+    where
+        B: Broadphase<usize>,
+    {
+        const WORLD_SIZE: i32 = 1000;
+        let mut reference = BruteForce::new(WORLD_SIZE, WORLD_SIZE);
+        for (id, raw) in raw_bounds.iter().enumerate() {
+            let bounds = to_bounds(raw, WORLD_SIZE);
+            world.add(id, bounds.clone());
+            reference.add(id, bounds);
+        }
+        for (id, &(x, y)) in new_positions.iter().enumerate().take(raw_bounds.len()) {
+            let moved = to_bounds(&(x, y, 0, 0), WORLD_SIZE);
+            let pos: Pos = Vector {
+                x: moved.x,
+                y: moved.y,
+            }.into();
+            world.place(&id, &pos);
+            reference.place(&id, &pos);
+        }
+        for id in removals {
+            let id = *id as usize;
+            if world.remove(&id) != reference.remove(&id) {
+                return false;
+            }
+        }
+        let query = to_bounds(raw_query, WORLD_SIZE);
+        sorted_pairs(&world) == sorted_pairs(&reference) &&
+            sorted_intersecting(&world, &query) == sorted_intersecting(&reference, &query)
+    }
+
+    fn world_with_walls() -> World<i32> {
+        let mut world = World::new(1000, 1000, 100);
+        let wall = Bounds {
+            x: 300,
+            y: 500,
+            width: 20,
+            height: 200,
+        };
+        world.add_filtered(1, wall.clone(), Filter::new(layer::WALL, layer::ALL));
+        world.add_filtered(
+            2,
+            Bounds { x: 600, ..wall.clone() },
+            Filter::new(layer::WALL, layer::ALL),
+        );
+        world.add_filtered(
+            3,
+            Bounds { x: 450, ..wall.clone() },
+            Filter::trigger(layer::GOAL, layer::ALL),
+        );
+        world.add_filtered(
+            4,
+            Bounds { x: 150, ..wall },
+            Filter::new(layer::PLAYER, layer::ALL),
+        );
+        world
+    }
+
+    #[test]
+    fn segment_cast_first() {
+        let world = world_with_walls();
+        let from = Vector { x: 200, y: 500 };
+        let to = Vector { x: 900, y: 500 };
+        let (id, hit) = world.segment_cast(&from, &to, layer::ALL).unwrap();
+        assert_eq!(1, id);
+        assert!((hit.distance - 90.0).abs() < 1e-9);
+        assert_eq!(Vector { x: -1, y: 0 }, hit.normal);
+    }
+
+    #[test]
+    fn segment_cast_all_sorted() {
+        let world = world_with_walls();
+        let from = Vector { x: 900, y: 450 };
+        let to = Vector { x: 0, y: 450 };
+        let hits = world.segment_cast_all(&from, &to, layer::ALL);
+        let ids = hits.iter().map(|&(id, _)| id).collect::<Vec<_>>();
+        assert_eq!(vec![2, 1, 4], ids);
+        assert!((hits[0].1.distance - 290.0).abs() < 1e-9);
+        assert_eq!(Vector { x: 1, y: 0 }, hits[0].1.normal);
+    }
+
+    #[test]
+    fn segment_cast_mask() {
+        let world = world_with_walls();
+        let from = Vector { x: 0, y: 500 };
+        let to = Vector { x: 1000, y: 500 };
+        let (id, _) = world.segment_cast(&from, &to, layer::WALL).unwrap();
+        assert_eq!(1, id);
+        let (id, _) = world.segment_cast(&from, &to, layer::PLAYER).unwrap();
+        assert_eq!(4, id);
+        assert!(world.segment_cast(&from, &to, layer::GOAL).is_none());
+    }
+
+    #[test]
+    fn segment_cast_from_inside() {
+        let world = world_with_walls();
+        let from = Vector { x: 300, y: 500 };
+        let to = Vector { x: 900, y: 500 };
+        let hits = world.segment_cast_all(&from, &to, layer::ALL);
+        let ids = hits.iter().map(|&(id, _)| id).collect::<Vec<_>>();
+        assert_eq!(vec![2], ids);
+        assert!((hits[0].1.distance - 290.0).abs() < 1e-9);
+        // Starting on the edge still counts as crossing the box
+        let from = Vector { x: 290, y: 500 };
+        let (id, hit) = world.segment_cast(&from, &to, layer::ALL).unwrap();
+        assert_eq!(1, id);
+        assert_eq!(0.0, hit.distance);
+    }
+
+    #[test]
+    fn segment_cast_too_short() {
+        let world = world_with_walls();
+        let from = Vector { x: 200, y: 500 };
+        let to = Vector { x: 250, y: 500 };
+        assert!(world.segment_cast(&from, &to, layer::ALL).is_none());
+    }
+
+    #[test]
+    fn raycast_until_world_edge() {
+        let world = world_with_walls();
+        let origin = Vector { x: 700, y: 500 };
+        let (id, hit) = world.raycast(&origin, &Vector { x: -3, y: 0 }, layer::ALL).unwrap();
+        assert_eq!(2, id);
+        assert!((hit.distance - 90.0).abs() < 1e-9);
+        assert!(world.raycast(&origin, &Vector { x: 1, y: 0 }, layer::ALL).is_none());
+        assert!(world.raycast(&origin, &Vector { x: 0, y: 0 }, layer::ALL).is_none());
+    }
+
+    #[test]
+    fn raycast_ignores_outside_of_world() {
+        let mut world = World::new(1000, 1000, 100);
+        world.add(
+            1,
+            Bounds {
+                x: 2,
+                y: 50,
+                width: 10,
+                height: 10,
+            },
+        );
+        let from = Vector { x: 50, y: 50 };
+        let (id, hit) = world
+            .segment_cast(&from, &Vector { x: -500, y: 50 }, layer::ALL)
+            .unwrap();
+        assert_eq!(1, id);
+        assert!((hit.distance - 43.0).abs() < 1e-9);
+
+        let outside = Vector { x: -50, y: 55 };
+        assert!(
+            world
+                .segment_cast(&outside, &Vector { x: -50, y: 45 }, layer::ALL)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn line_of_sight() {
+        let world = world_with_walls();
+        let a = Vector { x: 350, y: 500 };
+        let b = Vector { x: 550, y: 500 };
+        let c = Vector { x: 350, y: 900 };
+        assert!(world.has_line_of_sight(&a, &b, layer::WALL));
+        assert!(world.has_line_of_sight(&a, &c, layer::WALL));
+        assert!(!world.has_line_of_sight(&a, &Vector { x: 900, y: 500 }, layer::WALL));
+    }
+
+    #[test]
+    fn clip_segment() {
+        let from = Vector { x: -100, y: 500 };
+        let delta = Vector { x: 1200, y: 0 };
+        let (enter, exit) = clip_to_world(&from, &delta, 1000, 1000).unwrap();
+        assert_eq!(100.0 / 1200.0, enter);
+        assert_eq!(1100.0 / 1200.0, exit);
+        let outside = Vector { x: -100, y: -5 };
+        assert!(clip_to_world(&outside, &delta, 1000, 1000).is_none());
+    }
+}
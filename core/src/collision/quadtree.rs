@@ -0,0 +1,262 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use model::comp::Pos;
+use super::{Bounds, Filter, CollisionObject, Broadphase, Extent, assert_in_world,
+            assert_valid_dimensions, report_if_intersecting};
+
+const MAX_ITEMS_PER_NODE: usize = 8;
+const MAX_DEPTH: u32 = 8;
+
+struct Node<Id> {
+    region: Extent,
+    depth: u32,
+    items: Vec<Id>,
+    children: Vec<Node<Id>>,
+}
+
+impl<Id> Node<Id>
+where
+    Id: Hash + Eq + Clone,
+{
+    fn new(region: Extent, depth: u32) -> Self {
+        Node {
+            region,
+            depth,
+            items: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    fn child_containing(&self, extent: &Extent) -> Option<usize> {
+        self.children.iter().position(
+            |child| child.region.contains(extent),
+        )
+    }
+
+    // Items are stored in the deepest node fully containing them.
+    // Removal walks down the same way, so it always finds them again.
+    fn insert(&mut self, id: Id, extent: &Extent, entities: &HashMap<Id, Bounds>) {
+        if let Some(index) = self.child_containing(extent) {
+            self.children[index].insert(id, extent, entities);
+            return;
+        }
+        self.items.push(id);
+        if self.children.is_empty() && self.items.len() > MAX_ITEMS_PER_NODE &&
+            self.depth < MAX_DEPTH
+        {
+            self.split(entities);
+        }
+    }
+
+    fn remove(&mut self, id: &Id, extent: &Extent) {
+        if let Some(index) = self.child_containing(extent) {
+            self.children[index].remove(id, extent);
+            return;
+        }
+        let pos = self.items.iter().position(|x| *x == *id).expect(
+            "Didn't find id in quadtree node",
+        );
+        self.items.remove(pos);
+    }
+
+    fn split(&mut self, entities: &HashMap<Id, Bounds>) {
+        let region = self.region.clone();
+        let center_x = region.left + (region.right - region.left) / 2;
+        let center_y = region.top + (region.bottom - region.top) / 2;
+        if center_x == region.left || center_y == region.top {
+            // Too small to split any further
+            return;
+        }
+        let quadrants = vec![
+            Extent {
+                left: region.left,
+                top: region.top,
+                right: center_x,
+                bottom: center_y,
+            },
+            Extent {
+                left: center_x,
+                top: region.top,
+                right: region.right,
+                bottom: center_y,
+            },
+            Extent {
+                left: region.left,
+                top: center_y,
+                right: center_x,
+                bottom: region.bottom,
+            },
+            Extent {
+                left: center_x,
+                top: center_y,
+                right: region.right,
+                bottom: region.bottom,
+            },
+        ];
+        let depth = self.depth + 1;
+        self.children = quadrants
+            .into_iter()
+            .map(|quadrant| Node::new(quadrant, depth))
+            .collect();
+
+        for id in self.items.split_off(0) {
+            let extent = Extent::of(&entities[&id]);
+            match self.child_containing(&extent) {
+                Some(index) => self.children[index].items.push(id),
+                None => self.items.push(id),
+            }
+        }
+    }
+}
+
+/// Recursively divides the world into quadrants wherever entities are crowded
+pub struct QuadTree<Id> {
+    width: i32,
+    height: i32,
+    root: Node<Id>,
+    entities: HashMap<Id, Bounds>,
+    filters: HashMap<Id, Filter>,
+}
+
+impl<Id> QuadTree<Id>
+where
+    Id: Hash + Eq + Clone,
+{
+    pub fn new(width: i32, height: i32) -> Self {
+        assert_valid_dimensions(width, height);
+        let region = Extent {
+            left: 0,
+            top: 0,
+            right: width,
+            bottom: height,
+        };
+        QuadTree {
+            width,
+            height,
+            root: Node::new(region, 0),
+            entities: HashMap::new(),
+            filters: HashMap::new(),
+        }
+    }
+
+    fn object<'a>(&'a self, id: &'a Id) -> CollisionObject<'a, Id> {
+        CollisionObject {
+            id,
+            bounds: &self.entities[id],
+            filter: &self.filters[id],
+        }
+    }
+
+    // Items can only intersect items of the same node or of nodes further up
+    fn query_node_intersects<'a, T>(
+        &'a self,
+        node: &'a Node<Id>,
+        ancestors: &mut Vec<&'a Id>,
+        cb: &mut T,
+    ) where
+        T: FnMut(CollisionObject<Id>, CollisionObject<Id>),
+    {
+        for (i, id) in node.items.iter().enumerate() {
+            for ancestor in ancestors.iter() {
+                report_if_intersecting(self.object(ancestor), self.object(id), cb);
+            }
+            for other_id in &node.items[i + 1..] {
+                report_if_intersecting(self.object(id), self.object(other_id), cb);
+            }
+        }
+        let ancestor_count = ancestors.len();
+        ancestors.extend(node.items.iter());
+        for child in &node.children {
+            self.query_node_intersects(child, ancestors, cb);
+        }
+        ancestors.truncate(ancestor_count);
+    }
+
+    fn query_node_candidates<'a, T>(&'a self, node: &'a Node<Id>, extent: &Extent, cb: &mut T)
+    where
+        T: FnMut(CollisionObject<Id>),
+    {
+        for id in &node.items {
+            cb(self.object(id));
+        }
+        for child in &node.children {
+            if child.region.overlaps(extent) {
+                self.query_node_candidates(child, extent, cb);
+            }
+        }
+    }
+}
+
+impl<Id> Broadphase<Id> for QuadTree<Id>
+where
+    Id: Hash + Eq + Clone,
+{
+    fn width(&self) -> i32 {
+        self.width
+    }
+
+    fn height(&self) -> i32 {
+        self.height
+    }
+
+    fn get(&self, id: &Id) -> Option<&Bounds> {
+        self.entities.get(id)
+    }
+
+    fn get_filter(&self, id: &Id) -> Option<&Filter> {
+        self.filters.get(id)
+    }
+
+    fn add_filtered(&mut self, id: Id, bounds: Bounds, filter: Filter) {
+        assert_in_world(self.width, self.height, &bounds);
+        let extent = Extent::of(&bounds);
+        let old = self.entities.insert(id.clone(), bounds);
+        assert!(
+            old.is_none(),
+            "Failed to add new entity: Id already registered"
+        );
+        self.filters.insert(id.clone(), filter);
+        self.root.insert(id, &extent, &self.entities);
+    }
+
+    fn place(&mut self, id: &Id, pos: &Pos) {
+        let mut bounds = self.entities
+            .get(id)
+            .expect("Failed to place entity: Id doesn't exist")
+            .clone();
+        let old_extent = Extent::of(&bounds);
+        bounds.x = pos.x;
+        bounds.y = pos.y;
+        assert_in_world(self.width, self.height, &bounds);
+        let new_extent = Extent::of(&bounds);
+        self.entities.insert(id.clone(), bounds);
+        if old_extent != new_extent {
+            self.root.remove(id, &old_extent);
+            self.root.insert(id.clone(), &new_extent, &self.entities);
+        }
+    }
+
+    fn remove(&mut self, id: &Id) -> Option<Bounds> {
+        let bounds = self.entities.remove(id);
+        if let Some(ref bounds) = bounds {
+            self.filters.remove(id);
+            self.root.remove(id, &Extent::of(bounds));
+        }
+        bounds
+    }
+
+    fn query_intersects<T>(&self, mut cb: T)
+    where
+        T: FnMut(CollisionObject<Id>, CollisionObject<Id>),
+    {
+        self.query_node_intersects(&self.root, &mut Vec::new(), &mut cb);
+    }
+
+    fn query_candidates<T>(&self, bounds: &Bounds, mut cb: T)
+    where
+        T: FnMut(CollisionObject<Id>),
+    {
+        // The root is always visited, as it holds everything sticking out of the world
+        self.query_node_candidates(&self.root, &Extent::of(bounds), &mut cb);
+    }
+}
@@ -0,0 +1,170 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::hash::Hash;
+use model::comp::Pos;
+use super::{Bounds, Filter, CollisionObject, Broadphase, Extent, assert_in_world,
+            assert_valid_dimensions, report_if_intersecting};
+
+type AxisEntry<Id> = (i32, Id);
+
+/// Keeps all entities sorted by their left edge, so that only entities
+/// overlapping on the x axis have to be checked against each other.
+/// As entities barely move between updates, re-sorting them is cheap.
+pub struct SweepAndPrune<Id> {
+    width: i32,
+    height: i32,
+    axis: Vec<AxisEntry<Id>>,
+    widest: i32,
+    entities: HashMap<Id, Bounds>,
+    filters: HashMap<Id, Filter>,
+}
+
+impl<Id> SweepAndPrune<Id>
+where
+    Id: Hash + Ord + Clone,
+{
+    pub fn new(width: i32, height: i32) -> Self {
+        assert_valid_dimensions(width, height);
+        SweepAndPrune {
+            width,
+            height,
+            axis: Vec::new(),
+            widest: 0,
+            entities: HashMap::new(),
+            filters: HashMap::new(),
+        }
+    }
+
+    fn object<'a>(&'a self, id: &'a Id) -> CollisionObject<'a, Id> {
+        CollisionObject {
+            id,
+            bounds: &self.entities[id],
+            filter: &self.filters[id],
+        }
+    }
+
+    fn index_of(&self, entry: &AxisEntry<Id>) -> usize {
+        self.axis.binary_search(entry).expect(
+            "Didn't find registered entity on axis",
+        )
+    }
+
+    /// Index of the first entry whose left edge is at least `left`
+    fn lower_bound(&self, left: i32) -> usize {
+        let search = self.axis.binary_search_by(|&(probe, _)| if probe < left {
+            Ordering::Less
+        } else {
+            Ordering::Greater
+        });
+        match search {
+            Ok(index) | Err(index) => index,
+        }
+    }
+}
+
+impl<Id> Broadphase<Id> for SweepAndPrune<Id>
+where
+    Id: Hash + Ord + Clone,
+{
+    fn width(&self) -> i32 {
+        self.width
+    }
+
+    fn height(&self) -> i32 {
+        self.height
+    }
+
+    fn get(&self, id: &Id) -> Option<&Bounds> {
+        self.entities.get(id)
+    }
+
+    fn get_filter(&self, id: &Id) -> Option<&Filter> {
+        self.filters.get(id)
+    }
+
+    fn add_filtered(&mut self, id: Id, bounds: Bounds, filter: Filter) {
+        assert_in_world(self.width, self.height, &bounds);
+        let extent = Extent::of(&bounds);
+        let old = self.entities.insert(id.clone(), bounds);
+        assert!(
+            old.is_none(),
+            "Failed to add new entity: Id already registered"
+        );
+        self.filters.insert(id.clone(), filter);
+        if extent.right - extent.left > self.widest {
+            self.widest = extent.right - extent.left;
+        }
+        let entry = (extent.left, id);
+        let index = match self.axis.binary_search(&entry) {
+            Ok(index) | Err(index) => index,
+        };
+        self.axis.insert(index, entry);
+    }
+
+    fn place(&mut self, id: &Id, pos: &Pos) {
+        let mut bounds = self.entities
+            .get(id)
+            .expect("Failed to place entity: Id doesn't exist")
+            .clone();
+        let old_left = Extent::of(&bounds).left;
+        bounds.x = pos.x;
+        bounds.y = pos.y;
+        assert_in_world(self.width, self.height, &bounds);
+        let new_left = Extent::of(&bounds).left;
+        self.entities.insert(id.clone(), bounds);
+
+        // Bubble the entry to its new place, which is usually close by
+        let mut index = self.index_of(&(old_left, id.clone()));
+        self.axis[index].0 = new_left;
+        while index > 0 && self.axis[index] < self.axis[index - 1] {
+            self.axis.swap(index, index - 1);
+            index -= 1;
+        }
+        while index + 1 < self.axis.len() && self.axis[index] > self.axis[index + 1] {
+            self.axis.swap(index, index + 1);
+            index += 1;
+        }
+    }
+
+    fn remove(&mut self, id: &Id) -> Option<Bounds> {
+        let bounds = self.entities.remove(id);
+        if let Some(ref bounds) = bounds {
+            self.filters.remove(id);
+            let index = self.index_of(&(Extent::of(bounds).left, id.clone()));
+            self.axis.remove(index);
+        }
+        bounds
+    }
+
+    fn query_intersects<T>(&self, mut cb: T)
+    where
+        T: FnMut(CollisionObject<Id>, CollisionObject<Id>),
+    {
+        for (i, &(_, ref id)) in self.axis.iter().enumerate() {
+            let right = Extent::of(&self.entities[id]).right;
+            for &(other_left, ref other_id) in &self.axis[i + 1..] {
+                if other_left >= right {
+                    break;
+                }
+                report_if_intersecting(self.object(id), self.object(other_id), &mut cb);
+            }
+        }
+    }
+
+    fn query_candidates<T>(&self, bounds: &Bounds, mut cb: T)
+    where
+        T: FnMut(CollisionObject<Id>),
+    {
+        let extent = Extent::of(bounds);
+        // Nothing starting further left than our widest entity can reach us
+        let first = self.lower_bound(extent.left - self.widest);
+        for &(left, ref id) in &self.axis[first..] {
+            if left >= extent.right {
+                break;
+            }
+            if Extent::of(&self.entities[id]).right > extent.left {
+                cb(self.object(id));
+            }
+        }
+    }
+}
@@ -0,0 +1,309 @@
+extern crate serde_json;
+
+use model::game::{Vector, Side, Id};
+use collision::{Broadphase, Bounds, Filter, layer};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Goal zones in the collision world, together with the side they belong to
+pub type GoalZones = HashMap<Id, Side>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Level {
+    pub width: i32,
+    pub height: i32,
+    #[serde(default)]
+    pub walls: Vec<Bounds>,
+    #[serde(default)]
+    pub goals: Vec<Goal>,
+    #[serde(default)]
+    pub spawn_points: Vec<Vector>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Goal {
+    pub bounds: Bounds,
+    /// Side that concedes a point when a ball enters the goal
+    pub side: Side,
+}
+
+#[derive(Debug)]
+pub enum LevelError {
+    Io(io::Error),
+    Parse(serde_json::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LevelError::Io(ref err) => write!(f, "Failed to read level: {}", err),
+            LevelError::Parse(ref err) => write!(f, "Failed to parse level: {}", err),
+            LevelError::Invalid(ref reason) => write!(f, "Invalid level: {}", reason),
+        }
+    }
+}
+
+impl Error for LevelError {
+    fn description(&self) -> &str {
+        match *self {
+            LevelError::Io(_) => "Failed to read level",
+            LevelError::Parse(_) => "Failed to parse level",
+            LevelError::Invalid(_) => "Invalid level",
+        }
+    }
+}
+
+impl From<io::Error> for LevelError {
+    fn from(err: io::Error) -> Self {
+        LevelError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for LevelError {
+    fn from(err: serde_json::Error) -> Self {
+        LevelError::Parse(err)
+    }
+}
+
+impl Default for Level {
+    /// An empty field without any obstacles
+    fn default() -> Self {
+        Level {
+            width: 1000,
+            height: 1000,
+            walls: Vec::new(),
+            goals: Vec::new(),
+            spawn_points: Vec::new(),
+        }
+    }
+}
+
+impl Level {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, LevelError> {
+        let mut json = String::new();
+        File::open(path)?.read_to_string(&mut json)?;
+        Level::parse(&json)
+    }
+
+    pub fn parse(json: &str) -> Result<Self, LevelError> {
+        let level: Level = serde_json::from_str(json)?;
+        level.validate()?;
+        Ok(level)
+    }
+
+    /// Adds all walls and goals to `world` as static entries
+    pub fn populate<B: Broadphase<Id>>(&self, world: &mut B) -> GoalZones {
+        for wall in &self.walls {
+            world.add_filtered(Id::new_v4(), wall.clone(), Filter::new(layer::WALL, layer::ALL));
+        }
+        let mut goal_zones = GoalZones::new();
+        for goal in &self.goals {
+            let id = Id::new_v4();
+            let filter = Filter::trigger(layer::GOAL, layer::BALL);
+            world.add_filtered(id, goal.bounds.clone(), filter);
+            goal_zones.insert(id, goal.side);
+        }
+        goal_zones
+    }
+
+    /// Whether something with the given bounds would be stuck in one of the walls
+    pub fn overlaps_wall(&self, bounds: &Bounds) -> bool {
+        self.walls.iter().any(|wall| wall.intersects(bounds))
+    }
+
+    fn validate(&self) -> Result<(), LevelError> {
+        if self.width <= 0 || self.height <= 0 {
+            return Err(LevelError::Invalid(format!(
+                "Size of {}x{} is not positive",
+                self.width,
+                self.height
+            )));
+        }
+        let zones = self.walls.iter().chain(self.goals.iter().map(|goal| &goal.bounds));
+        for bounds in zones {
+            if bounds.width <= 0 || bounds.height <= 0 || !self.covers(bounds) {
+                return Err(LevelError::Invalid(
+                    format!("{:?} is empty or outside of the level", bounds),
+                ));
+            }
+        }
+        for point in &self.spawn_points {
+            if point.x < 0 || point.y < 0 || point.x > self.width || point.y > self.height {
+                return Err(LevelError::Invalid(
+                    format!("Spawn point {:?} is outside of the level", point),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn covers(&self, bounds: &Bounds) -> bool {
+        let level = Bounds {
+            x: self.width / 2,
+            y: self.height / 2,
+            width: self.width,
+            height: self.height,
+        };
+        level.intersects(bounds)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use collision::World;
+
+    #[test]
+    fn parse_minimal() {
+        let level = Level::parse(r#"{"width": 800, "height": 600}"#).unwrap();
+        assert_eq!(800, level.width);
+        assert_eq!(600, level.height);
+        assert!(level.walls.is_empty());
+        assert!(level.goals.is_empty());
+        assert!(level.spawn_points.is_empty());
+    }
+
+    #[test]
+    fn parse_full() {
+        let json = r#"{
+            "width": 1000,
+            "height": 1000,
+            "walls": [{"x": 500, "y": 500, "width": 100, "height": 20}],
+            "goals": [{"bounds": {"x": 5, "y": 500, "width": 10, "height": 1000}, "side": "Left"}],
+            "spawn_points": [{"x": 20, "y": 500}, {"x": 980, "y": 500}]
+        }"#;
+        let level = Level::parse(json).unwrap();
+        assert_eq!(1, level.walls.len());
+        assert_eq!(Side::Left, level.goals[0].side);
+        assert_eq!(Vector { x: 980, y: 500 }, level.spawn_points[1]);
+    }
+
+    #[test]
+    fn parse_malformed() {
+        match Level::parse(r#"{"width": 800"#) {
+            Err(LevelError::Parse(_)) => {}
+            other => panic!("Expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reject_empty_size() {
+        match Level::parse(r#"{"width": 0, "height": 600}"#) {
+            Err(LevelError::Invalid(_)) => {}
+            other => panic!("Expected an invalid level, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reject_wall_outside() {
+        let json = r#"{
+            "width": 1000,
+            "height": 1000,
+            "walls": [{"x": 1500, "y": 500, "width": 100, "height": 20}]
+        }"#;
+        match Level::parse(json) {
+            Err(LevelError::Invalid(_)) => {}
+            other => panic!("Expected an invalid level, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reject_spawn_point_outside() {
+        let json = r#"{"width": 1000, "height": 1000, "spawn_points": [{"x": -1, "y": 0}]}"#;
+        match Level::parse(json) {
+            Err(LevelError::Invalid(_)) => {}
+            other => panic!("Expected an invalid level, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn populate_world() {
+        let level = Level {
+            walls: vec![
+                Bounds {
+                    x: 500,
+                    y: 500,
+                    width: 100,
+                    height: 20,
+                },
+            ],
+            goals: vec![
+                Goal {
+                    bounds: Bounds {
+                        x: 5,
+                        y: 500,
+                        width: 10,
+                        height: 1000,
+                    },
+                    side: Side::Left,
+                },
+            ],
+            ..Level::default()
+        };
+        let mut world = World::new(level.width, level.height, 100);
+        let goal_zones = level.populate(&mut world);
+        assert_eq!(1, goal_zones.len());
+
+        let mut walls = 0;
+        let mut goals = 0;
+        let everything = Bounds {
+            x: 500,
+            y: 500,
+            width: 1000,
+            height: 1000,
+        };
+        world.query_intersects_other(&everything, |other| {
+            if other.filter.layer == layer::WALL {
+                walls += 1;
+            } else if other.filter.layer == layer::GOAL {
+                assert!(other.filter.is_trigger);
+                assert_eq!(Some(&Side::Left), goal_zones.get(other.id));
+                goals += 1;
+            }
+        });
+        assert_eq!(1, walls);
+        assert_eq!(1, goals);
+    }
+
+    #[test]
+    fn overlap_walls() {
+        let level = Level {
+            walls: vec![
+                Bounds {
+                    x: 500,
+                    y: 500,
+                    width: 100,
+                    height: 20,
+                },
+            ],
+            ..Level::default()
+        };
+        let bounds = |x, y| {
+            Bounds {
+                x,
+                y,
+                width: 30,
+                height: 30,
+            }
+        };
+        assert!(level.overlaps_wall(&bounds(500, 500)));
+        assert!(level.overlaps_wall(&bounds(460, 520)));
+        // Touching is fine
+        assert!(!level.overlaps_wall(&bounds(500, 525)));
+        assert!(!Level::default().overlaps_wall(&bounds(500, 500)));
+    }
+
+    #[test]
+    fn load_bundled_levels() {
+        for name in &["classic", "arena", "four_player"] {
+            let path = format!("{}/levels/{}.json", env!("CARGO_MANIFEST_DIR"), name);
+            Level::load(&path).expect(&format!("Failed to load {}", path));
+        }
+    }
+}
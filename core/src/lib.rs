@@ -14,6 +14,10 @@ extern crate derive_more;
 #[macro_use]
 extern crate maplit;
 
+#[cfg(test)]
+#[macro_use]
+extern crate quickcheck;
+
 
 #[macro_use]
 pub mod util;
@@ -22,3 +26,12 @@ pub mod model;
 pub mod system;
 pub mod bootstrap;
 pub mod collision;
+pub mod level;
+pub mod priority;
+pub mod admin;
+pub mod clock;
+pub mod shutdown;
+pub mod results;
+pub mod name;
+pub mod rating;
+pub mod chat;
@@ -1,87 +1,332 @@
 extern crate shootr;
 
 extern crate specs;
-extern crate chrono;
+extern crate futures;
+extern crate serde;
 extern crate serde_json;
 extern crate websocket_server;
 extern crate dotenv;
 
-use specs::{DispatcherBuilder, World, Entity};
-use chrono::prelude::*;
+use specs::{DispatcherBuilder, World, Entity, Join};
 use websocket_server::{start as start_server, EventHandler, SendChannel, Message};
+use futures::{Future, Sink};
+use serde::ser::Serialize;
 use dotenv::dotenv;
 
-use shootr::util::{read_env_var, elapsed_ms, SeqIdGen};
-use shootr::model::comp::{ToSpawn, ToDespawn, Player, Actor, ActorKind};
-use shootr::model::network::ClientMsg;
-use shootr::model::game::Id;
+use shootr::util::{read_env_var, timestamp, Time};
+use shootr::model::comp::{ToSpawn, ToDespawn, Respawn, Player, Actor, ActorKind, Friction,
+                          Team, Score, Stats, Name};
+use shootr::model::network::{ClientMsg, ClientRequest, SendConfig, Pong, Shutdown, ServerMsg,
+                             ChatMessage};
+use shootr::model::game::{Id, GameMode, BallCount, FriendlyFire, ViewSize};
 use shootr::system::*;
 use shootr::bootstrap;
-use shootr::collision::World as CollisionWorld;
+use shootr::level::Level;
+use shootr::admin::{self, AdminCommand, PlayerStatus};
+use shootr::clock::{GameClock, SystemClock};
+use shootr::shutdown;
+use shootr::name;
+use shootr::rating::Ratings;
+use shootr::chat::{Chat, ChatConfig, ChatError};
+use shootr::results::{MatchResult, PlayerResult, MatchRecord, ResultStore, JsonLinesStore,
+                      MemoryStore};
 
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, RwLock, Mutex};
 use std::thread::sleep;
-use std::time::Duration;
 use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
 use std::net::SocketAddr;
+use std::env;
+use std::time::Duration;
+
+/// Updates that are run at most to catch up after the server fell behind
+const MAX_CATCH_UP: u32 = 5;
+/// Time in ms a new client has to say hello before it gets spawned under a fallback name
+const HELLO_TIMEOUT_MS: Time = 3000;
+/// Time the connections get to send out their last messages before the server stops
+const SHUTDOWN_GRACE_MS: u64 = 500;
 
 fn main() {
     dotenv().ok();
+    shutdown::handle_signals();
     let port = read_env_var("CORE_PORT").parse::<u32>().expect(
         "Specified port is not a valid number",
     );
     start_server::<Handler>("localhost", port);
 }
 
+/// Final standing of a player, read off the world
+fn player_result(world: &World, entity: Entity, id: Id, disconnected: bool) -> PlayerResult {
+    let stats = world.read::<Stats>().get(entity).cloned().unwrap_or_default();
+    PlayerResult {
+        id,
+        name: world.read::<Name>().get(entity).map(|name| name.0.clone()),
+        team: world.read::<Team>().get(entity).cloned(),
+        score: world.read::<Score>().get(entity).map(|score| score.0),
+        hits: stats.hits,
+        deaths: stats.deaths,
+        disconnected,
+    }
+}
+
+/// Results are only kept in memory unless a file is configured
+fn open_results() -> Box<ResultStore + Send> {
+    match env::var("CORE_RESULTS_FILE") {
+        Ok(path) => Box::new(JsonLinesStore::new(path)),
+        Err(_) => Box::new(MemoryStore::default()),
+    }
+}
+
+fn chat_config() -> ChatConfig {
+    let max_length = read_env_var("CORE_CHAT_MAX_LENGTH").parse::<usize>().expect(
+        "Failed to parse environmental variable as integer",
+    );
+    let messages_per_window = read_env_var("CORE_CHAT_MESSAGES").parse::<usize>().expect(
+        "Failed to parse environmental variable as integer",
+    );
+    let window = read_env_var("CORE_CHAT_WINDOW_MS").parse::<Time>().expect(
+        "Failed to parse environmental variable as integer",
+    );
+    let blocked_words = read_env_var("CORE_CHAT_BLOCKED_WORDS")
+        .split(',')
+        .map(|word| word.trim().to_lowercase())
+        .filter(|word| !word.is_empty())
+        .collect();
+    ChatConfig {
+        max_length,
+        messages_per_window,
+        window,
+        blocked_words,
+    }
+}
+
+/// Sends a message right away instead of waiting for the next run of `Sending`
+fn send_directly<T>(id: &Id, send_channel: &SendChannel, msg: &ServerMsg<T>)
+where
+    T: Serialize + Debug,
+{
+    let msg = serde_json::to_string(msg).expect(&format!("Failed to serialize object {:?}", msg));
+    // The client might be gone already, which is no reason to stop the server
+    if send_channel.clone().send(Message::Text(msg)).wait().is_err() {
+        println!("Client {}: Failed to send message", id);
+    }
+}
+
 struct Handler {
     id_entity: RwLock<HashMap<Id, Entity>>,
-    to_spawn: RwLock<HashMap<Id, SendChannel>>,
+    /// Connections that wait for their hello before they get spawned, with the time they connected
+    to_spawn: RwLock<HashMap<Id, (SendChannel, Time)>>,
+    /// Names of clients that already said hello, kept until they leave
+    names: RwLock<HashMap<Id, String>>,
+    /// Every open connection, for messages that don't wait for the next world update,
+    /// like chat passed on by the websocket threads or closing the connection at shutdown
+    connections: RwLock<HashMap<Id, SendChannel>>,
+    chat: RwLock<Chat>,
     to_despawn: RwLock<HashSet<Id>>,
     inputs: Arc<RwLock<HashMap<Id, Vec<ClientMsg>>>>,
+    team_choices: TeamChoices,
+    send_rates: SendRates,
+    /// Pongs together with the server time they arrived at
+    pongs: RwLock<Vec<(Id, Pong, Time)>>,
+    /// Clients that want the game to be paused
+    pause_votes: RwLock<HashSet<Id>>,
+    results: Mutex<Box<ResultStore + Send>>,
+    /// Time in ms after which a match ends, unless everybody left before
+    match_length: Time,
 }
 
 impl Handler {
     fn prepare_world(&self, world: &mut World) {
-        bootstrap::prepare_world(world);
+        let level_path = read_env_var("CORE_LEVEL");
+        let level = Level::load(&level_path).unwrap_or_else(|err| {
+            panic!("Failed to load level {}: {}", level_path, err)
+        });
+        bootstrap::prepare_world(world, level);
         world.add_resource(self.inputs.clone());
-        world.add_resource(RwLock::new(CollisionWorld::<Id>::new(1000, 1000)));
+        world.add_resource(self.team_choices.clone());
+        world.add_resource(self.send_rates.clone());
 
-        // Create ball
-        let id = Id::new_v4();
-        let entity = world
-            .create_entity()
-            .with(ToSpawn {})
-            .with(Actor {
-                id,
-                kind: ActorKind::Ball,
-            })
-            .build();
-        self.id_entity.write().unwrap().insert(id, entity);
+        let mode = read_env_var("CORE_GAME_MODE").parse::<GameMode>().expect(
+            "Specified game mode is not valid",
+        );
+        let friction = read_env_var("CORE_ARENA_FRICTION").parse::<i32>().expect(
+            "Failed to parse environmental variable as integer",
+        );
+        world.add_resource(Friction(friction));
+        let ball_count = read_env_var("CORE_BALL_COUNT").parse::<usize>().expect(
+            "Failed to parse environmental variable as integer",
+        );
+        // The balls themselves get created by `Scoring` as soon as the game starts
+        world.add_resource(BallCount(ball_count));
+        let friendly_fire = read_env_var("CORE_FRIENDLY_FIRE").parse::<bool>().expect(
+            "Failed to parse environmental variable as boolean",
+        );
+        world.add_resource(FriendlyFire(friendly_fire));
+        let view_width = read_env_var("CORE_VIEW_WIDTH").parse::<i32>().expect(
+            "Failed to parse environmental variable as integer",
+        );
+        let view_height = read_env_var("CORE_VIEW_HEIGHT").parse::<i32>().expect(
+            "Failed to parse environmental variable as integer",
+        );
+        world.add_resource(ViewSize {
+            width: view_width,
+            height: view_height,
+        });
+        let updates_per_sec = read_env_var("CORE_UPDATES_PER_SEC").parse::<u32>().expect(
+            "Failed to parse environmental variable as integer",
+        );
+        world.add_resource(GameClock::new(
+            SystemClock::default(),
+            updates_per_sec,
+            MAX_CATCH_UP,
+        ));
+        let default_rate = read_env_var("CORE_SEND_RATE").parse::<u32>().expect(
+            "Failed to parse environmental variable as integer",
+        );
+        let byte_budget = read_env_var("CORE_BYTE_BUDGET").parse::<usize>().expect(
+            "Failed to parse environmental variable as integer",
+        );
+        world.add_resource(SendConfig {
+            updates_per_sec,
+            default_rate,
+            byte_budget,
+        });
+        world.add_resource(mode);
+        world.add_resource(MatchRecord::new(timestamp()));
+        let matches = self.results.lock().unwrap().matches().unwrap_or_else(|err| {
+            println!("Starting without ratings: {}", err);
+            Vec::new()
+        });
+        world.add_resource(Ratings::from_matches(&matches));
     }
 
 
     fn handle_msg(&self, id: Id, msg: &str) {
-        if let Ok(key_state) = serde_json::from_str::<ClientMsg>(msg) {
-            let mut inputs = self.inputs.write().unwrap();
-            let has_already_inputs = inputs.get(&id).is_some();
-            if has_already_inputs {
-                inputs.get_mut(&id).unwrap().push(key_state);
-            } else {
-                inputs.insert(id, vec![key_state]);
+        let request = serde_json::from_str::<ClientRequest>(msg);
+        match request {
+            Ok(ClientRequest::Hello(_)) | Err(_) => {}
+            // Clients that don't know about hellos shouldn't have to wait for the timeout
+            Ok(_) => self.introduce(id, None),
+        }
+        match request {
+            Ok(ClientRequest::Hello(hello)) => {
+                let name = name::validate(&hello.name).unwrap_or_else(|err| {
+                    println!("Client {}: Chose an invalid name: {}", id, err);
+                    name::fallback(&id)
+                });
+                self.introduce(id, Some(name));
+            }
+            Ok(ClientRequest::Input(key_state)) => {
+                let mut inputs = self.inputs.write().unwrap();
+                let has_already_inputs = inputs.get(&id).is_some();
+                if has_already_inputs {
+                    inputs.get_mut(&id).unwrap().push(key_state);
+                } else {
+                    inputs.insert(id, vec![key_state]);
+                }
+            }
+            Ok(ClientRequest::JoinTeam(team)) => {
+                self.team_choices.write().unwrap().insert(id, team);
+            }
+            Ok(ClientRequest::SetSendRate(rate)) => {
+                self.send_rates.write().unwrap().insert(id, rate);
+            }
+            Ok(ClientRequest::Pong(pong)) => {
+                self.pongs.write().unwrap().push((id, pong, timestamp()));
+            }
+            Ok(ClientRequest::VotePause(true)) => {
+                self.pause_votes.write().unwrap().insert(id);
+            }
+            Ok(ClientRequest::VotePause(false)) => {
+                self.pause_votes.write().unwrap().remove(&id);
+            }
+            Ok(ClientRequest::Chat(text)) => self.pass_on_chat(id, &text),
+            Ok(ClientRequest::JoinRoom(room)) => {
+                let joined = self.chat.write().unwrap().join(id, &room);
+                match joined {
+                    Ok(room) => self.confirm_join(id, &room),
+                    Err(err) => self.reject_chat(id, &err),
+                }
+            }
+            Err(_) => println!("Client {}: Sent invalid message: {}", id, msg),
+        }
+    }
+
+    /// Remembers the name of a client, unless it already has one
+    fn introduce(&self, id: Id, name: Option<String>) {
+        let mut names = self.names.write().unwrap();
+        if names.contains_key(&id) {
+            return;
+        }
+        let name = name.unwrap_or_else(|| name::fallback(&id));
+        println!("Client {}: Introduced as {}", id, name);
+        names.insert(id, name);
+    }
+
+    fn pass_on_chat(&self, id: Id, text: &str) {
+        let post = match self.chat.write().unwrap().post(id, text, timestamp()) {
+            Ok(post) => post,
+            Err(err) => {
+                self.reject_chat(id, &err);
+                return;
+            }
+        };
+        let name = self.names.read().unwrap().get(&id).cloned().unwrap_or_else(
+            || name::fallback(&id),
+        );
+        let msg = ServerMsg::new_chat(&ChatMessage {
+            room: post.room.clone(),
+            sender: id,
+            name,
+            text: post.text,
+            sent: timestamp(),
+        });
+        let members = self.chat.read().unwrap().members(&post.room);
+        let channels = self.connections.read().unwrap();
+        for member in members {
+            if let Some(send_channel) = channels.get(&member) {
+                send_directly(&member, send_channel, &msg);
             }
-        } else {
-            println!("Client {}: Sent invalid message: {}", id, msg);
+        }
+    }
+
+    fn confirm_join(&self, id: Id, room: &str) {
+        if let Some(send_channel) = self.connections.read().unwrap().get(&id) {
+            send_directly(&id, send_channel, &ServerMsg::new_room_joined(room));
+        }
+    }
+
+    fn reject_chat(&self, id: Id, err: &ChatError) {
+        if let Some(send_channel) = self.connections.read().unwrap().get(&id) {
+            send_directly(&id, send_channel, &ServerMsg::new_chat_rejected(&err.to_string()));
         }
     }
 
     fn register_connections(&self, world: &mut World) {
         let mut id_entity = self.id_entity.write().unwrap();
         let mut to_spawn = self.to_spawn.write().unwrap();
-        for (id, send_channel) in to_spawn.drain() {
+        let now = timestamp();
+        let ready = {
+            let names = self.names.read().unwrap();
+            to_spawn
+                .iter()
+                .filter(|&(id, &(_, connected))| {
+                    names.contains_key(id) || connected + HELLO_TIMEOUT_MS <= now
+                })
+                .map(|(id, _)| *id)
+                .collect::<Vec<_>>()
+        };
+        for id in ready {
+            // Does nothing for clients that said hello in time
+            self.introduce(id, None);
+            let (send_channel, _) = to_spawn.remove(&id).unwrap();
+            let name = self.names.read().unwrap()[&id].clone();
             let entity = world
                 .create_entity()
                 .with(ToSpawn {})
                 .with(Player::new(send_channel))
+                .with(Name(name))
+                .with(Stats::default())
                 .with(Actor {
                     id,
                     kind: ActorKind::Player,
@@ -92,11 +337,183 @@ impl Handler {
 
         let mut to_despawn = self.to_despawn.write().unwrap();
         for id in to_despawn.drain() {
+            // Clients might leave before they said hello
+            to_spawn.remove(&id);
+            self.names.write().unwrap().remove(&id);
+            self.team_choices.write().unwrap().remove(&id);
+            self.send_rates.write().unwrap().remove(&id);
+            self.pause_votes.write().unwrap().remove(&id);
             if let Some(entity) = id_entity.remove(&id) {
+                let result = player_result(world, entity, id, true);
+                world.write_resource::<MatchRecord>().departed.push(result);
+                // Dead players would otherwise be kept around for their respawn
+                world.write::<Respawn>().remove(entity);
                 world.write::<ToDespawn>().insert(entity, ToDespawn {});
             }
         }
     }
+
+    fn measure_latencies(&self, world: &mut World) {
+        let id_entity = self.id_entity.read().unwrap();
+        let mut players = world.write::<Player>();
+        for (id, pong, received_at) in self.pongs.write().unwrap().drain(..) {
+            // The client might have left in the meantime
+            let entity = match id_entity.get(&id) {
+                Some(entity) => *entity,
+                None => continue,
+            };
+            if let Some(player) = players.get_mut(entity) {
+                player.latency.add_sample(&pong, received_at);
+                player.latency_changed = true;
+            }
+        }
+    }
+
+    fn count_pause_votes(&self, world: &World) {
+        let id_entity = self.id_entity.read().unwrap();
+        let votes = self.pause_votes.read().unwrap();
+        // Everybody in the match has to agree
+        let agreed = !id_entity.is_empty() && id_entity.keys().all(|id| votes.contains(id));
+        world.write_resource::<GameClock>().set_paused_by_players(agreed);
+    }
+
+    /// Keeps only the latest state of every key while the game is paused,
+    /// so the inputs neither pile up nor all take effect at once when it resumes
+    fn collapse_inputs(&self) {
+        for key_states in self.inputs.write().unwrap().values_mut() {
+            let mut latest: Vec<ClientMsg> = Vec::new();
+            for key_state in key_states.drain(..) {
+                latest.retain(|other| other.command != key_state.command);
+                latest.push(key_state);
+            }
+            *key_states = latest;
+        }
+    }
+
+    /// Asks every client to close its connection
+    fn close_connections(&self) {
+        for (id, send_channel) in self.connections.read().unwrap().iter() {
+            if send_channel.clone().send(Message::Close(None)).wait().is_err() {
+                println!("Client {}: Failed to close connection", id);
+            }
+        }
+    }
+
+    /// A match starts with its first player and lasts until everybody left or its time is up
+    fn end_match_if_over(&self, world: &World) {
+        let now = timestamp();
+        let is_empty = self.id_entity.read().unwrap().is_empty();
+        let is_over = {
+            let mut record = world.write_resource::<MatchRecord>();
+            if is_empty && record.departed.is_empty() {
+                // Nobody played yet, so the match hasn't started
+                record.started = now;
+            }
+            let time_is_up = now.saturating_sub(record.started) >= self.match_length;
+            (is_empty && !record.departed.is_empty()) || (!is_empty && time_is_up)
+        };
+        if is_over {
+            self.end_match(world);
+        }
+    }
+
+    /// Records the result of the running match and starts a new one with everybody still around
+    fn end_match(&self, world: &World) {
+        let result = self.match_result(world);
+        if !result.players.is_empty() {
+            println!("Match over after {}s", result.duration() / 1000);
+            if let Err(err) = self.results.lock().unwrap().record(&result) {
+                println!("{}", err);
+            }
+            // The next teams get balanced with the outcome of this match already
+            world.write_resource::<Ratings>().update(&result);
+        }
+        *world.write_resource::<MatchRecord>() = MatchRecord::new(timestamp());
+        for stats in (&mut world.write::<Stats>()).join() {
+            *stats = Stats::default();
+        }
+        for score in (&mut world.write::<Score>()).join() {
+            score.0 = 0;
+        }
+    }
+
+    fn match_result(&self, world: &World) -> MatchResult {
+        let mut results = world.read_resource::<MatchRecord>().departed.clone();
+        for (id, entity) in self.id_entity.read().unwrap().iter() {
+            results.push(player_result(world, *entity, *id, false));
+        }
+        MatchResult {
+            started: world.read_resource::<MatchRecord>().started,
+            finished: timestamp(),
+            mode: world.read_resource::<GameMode>().clone(),
+            players: results,
+        }
+    }
+
+    fn run_admin_command(&self, command: AdminCommand, world: &World) {
+        match command {
+            AdminCommand::Shutdown(reason) => {
+                world.write_resource::<Shutdown>().reason = reason;
+                shutdown::request();
+            }
+            AdminCommand::Pause => {
+                world.write_resource::<GameClock>().set_paused_by_admin(true);
+                println!("Paused");
+            }
+            AdminCommand::Resume => {
+                self.pause_votes.write().unwrap().clear();
+                let mut clock = world.write_resource::<GameClock>();
+                clock.set_paused_by_admin(false);
+                clock.set_paused_by_players(false);
+                println!("Resumed");
+            }
+            AdminCommand::Step(steps) => {
+                let mut clock = world.write_resource::<GameClock>();
+                clock.step(steps);
+                println!("Stepping {} update(s) from tick {}", steps, clock.tick());
+            }
+            AdminCommand::Status => {
+                let entities = world.entities();
+                let actors = world.read::<Actor>();
+                let players = world.read::<Player>();
+                let names = world.read::<Name>();
+                let teams = world.read::<Team>();
+                let scores = world.read::<Score>();
+                let mut statuses = Vec::new();
+                for (entity, actor, player) in (&*entities, &actors, &players).join() {
+                    let latency = if player.latency.samples > 0 {
+                        Some(player.latency.clone())
+                    } else {
+                        None
+                    };
+                    statuses.push(PlayerStatus {
+                        id: actor.id,
+                        name: names.get(entity).map(|name| name.0.clone()),
+                        team: teams.get(entity).cloned(),
+                        score: scores.get(entity).map(|score| score.0),
+                        latency,
+                    });
+                }
+                println!("{}", admin::format_status(&statuses));
+            }
+            AdminCommand::Matches(count) => {
+                match self.results.lock().unwrap().recent_matches(count) {
+                    Ok(matches) => println!("{}", admin::format_matches(&matches)),
+                    Err(err) => println!("{}", err),
+                }
+            }
+            AdminCommand::Ratings(count) => {
+                let ratings = world.read_resource::<Ratings>().top(count);
+                println!("{}", admin::format_ratings(&ratings));
+            }
+            AdminCommand::Leaderboard(count) => {
+                match self.results.lock().unwrap().leaderboard(count) {
+                    Ok(entries) => println!("{}", admin::format_leaderboard(&entries)),
+                    Err(err) => println!("{}", err),
+                }
+            }
+        }
+    }
 }
 
 impl EventHandler for Handler {
@@ -106,8 +523,19 @@ impl EventHandler for Handler {
         Handler {
             id_entity: RwLock::new(HashMap::new()),
             to_spawn: RwLock::new(HashMap::new()),
+            names: RwLock::new(HashMap::new()),
+            connections: RwLock::new(HashMap::new()),
+            chat: RwLock::new(Chat::new(chat_config())),
             to_despawn: RwLock::new(HashSet::new()),
             inputs: Arc::new(RwLock::new(HashMap::new())),
+            team_choices: Arc::new(RwLock::new(HashMap::new())),
+            send_rates: Arc::new(RwLock::new(HashMap::new())),
+            pongs: RwLock::new(Vec::new()),
+            pause_votes: RwLock::new(HashSet::new()),
+            results: Mutex::new(open_results()),
+            match_length: read_env_var("CORE_MATCH_LENGTH_S").parse::<Time>().expect(
+                "Failed to parse environmental variable as integer",
+            ) * 1000,
         }
     }
     fn main_loop(&self) {
@@ -116,42 +544,73 @@ impl EventHandler for Handler {
 
         let mut updater = DispatcherBuilder::new()
             .add(InputHandler, "input_handler", &[])
-            .add(Spawn, "spawn", &["input_handler"])
+            .add(Shooting, "shooting", &["input_handler"])
+            .add(Spawn, "spawn", &["shooting"])
             .add(Physics, "physics", &["spawn"])
             .add(Bounce, "bounce", &["physics"])
+            .add(
+                CollisionDetection::default(),
+                "collision_detection",
+                &["bounce"],
+            )
+            .add(Projectiles, "projectiles", &["bounce"])
+            .add(DamageHandler, "damage_handler", &["projectiles"])
+            .add(Scoring, "scoring", &["collision_detection"])
+            .add(PowerUps::default(), "power_ups", &["collision_detection"])
             .build();
         // Initial update
         updater.dispatch(&mut world.res);
+        world.write_resource::<GameClock>().finish_update();
 
         let mut sender = DispatcherBuilder::new()
             .add(Sending, "sending", &[])
             .add(Despawn, "despawn", &["sending"])
             .build();
 
-        let mut lag: u64 = 0;
-        let mut previous = Utc::now();
-        let updates_per_sec = read_env_var("CORE_UPDATES_PER_SEC").parse::<u64>().expect(
-            "Failed to parse environmental variable as integer",
-        );
-        let ms_per_update = 1000 / updates_per_sec;
-        let mut curr_tick_generator = SeqIdGen::default();
-        loop {
-            let current = Utc::now();
-            let elapsed = elapsed_ms(previous, current).expect("Time went backwards");
-            previous = current;
-            lag += elapsed;
-            world.add_resource(curr_tick_generator.gen());
-
+        let console = admin::start_console();
+        while !shutdown::is_requested() {
             self.register_connections(&mut world);
-            while lag >= ms_per_update {
+            self.measure_latencies(&mut world);
+            self.count_pause_votes(&world);
+            self.end_match_if_over(&world);
+            for command in console.try_iter() {
+                self.run_admin_command(command, &world);
+            }
+
+            let (due_updates, is_paused) = {
+                let mut clock = world.write_resource::<GameClock>();
+                (clock.due_updates(), clock.is_paused())
+            };
+            if is_paused && due_updates == 0 {
+                self.collapse_inputs();
+            }
+            for _ in 0..due_updates {
                 updater.dispatch(&mut world.res);
                 world.maintain();
-                lag -= ms_per_update;
+                world.write_resource::<GameClock>().finish_update();
             }
             sender.dispatch(&mut world.res);
 
-            sleep(Duration::from_millis(ms_per_update - lag));
+            let wait = {
+                let mut clock = world.write_resource::<GameClock>();
+                clock.finish_send();
+                clock.until_next_update()
+            };
+            sleep(wait);
         }
+
+        let reason = world.read_resource::<Shutdown>().reason.clone().unwrap_or_else(
+            || "Server is shutting down".to_owned(),
+        );
+        println!("Shutting down: {}", reason);
+        world.write_resource::<Shutdown>().reason = Some(reason);
+        // One last round tells every player about it
+        sender.dispatch(&mut world.res);
+        self.close_connections();
+        self.end_match(&world);
+        // The messages are only queued so far. They get written by the event loop of the
+        // websocket server, which stops as soon as we return.
+        sleep(Duration::from_millis(SHUTDOWN_GRACE_MS));
     }
 
     fn on_message(&self, id: Self::Id, msg: Message) {
@@ -160,13 +619,20 @@ impl EventHandler for Handler {
         };
     }
     fn on_connect(&self, _: SocketAddr, send_channel: SendChannel) -> Option<Self::Id> {
+        if shutdown::is_requested() {
+            return None;
+        }
         let id = Id::new_v4();
-        self.to_spawn.write().unwrap().insert(id, send_channel);
+        self.connections.write().unwrap().insert(id, send_channel.clone());
+        self.chat.write().unwrap().connect(id);
+        self.to_spawn.write().unwrap().insert(id, (send_channel, timestamp()));
         println!("Client {}: Connected", id);
         Some(id)
     }
     fn on_disconnect(&self, id: Self::Id) {
         println!("Client {}: Disconnected", id);
+        self.connections.write().unwrap().remove(&id);
+        self.chat.write().unwrap().disconnect(&id);
         self.to_despawn.write().unwrap().insert(id);
     }
 }
@@ -3,11 +3,12 @@ extern crate websocket_server;
 use self::specs::{Component, DenseVecStorage};
 use self::websocket_server::SendChannel;
 
-use super::game::{Vector, Id as GameId};
-use model::network::Command;
+use super::game::{Vector, Side, Id as GameId};
+use model::network::{Command, Latency};
+use priority::PriorityAccumulator;
 use std::ops::{Deref, DerefMut};
 use std::convert::From;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 vectype!(Acc);
 vectype!(Vel);
@@ -18,11 +19,19 @@ vectype!(Pos);
 pub struct Bounciness {}
 
 newtype!(Friction(i32): Debug, Clone, Serialize, Component);
+newtype!(Health(i32): Debug, Clone, Serialize, Component);
+newtype!(Score(u32): Debug, Clone, Serialize, Component);
+/// Players of a team share a side in the pong modes and fight together in the others
+newtype!(Team(u8): Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Component);
+/// Display name a player introduced themselves with
+newtype!(Name(String): Debug, Clone, PartialEq, Eq, Serialize, Component);
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub enum ActorKind {
     Player,
     Ball,
+    Projectile,
+    Pickup,
 }
 
 
@@ -32,6 +41,124 @@ pub struct Actor {
     pub kind: ActorKind,
 }
 
+#[derive(Debug, Clone, Component)]
+pub struct Weapon {
+    pub is_firing: bool,
+    /// Updates left until the next shot can be fired
+    pub cooldown: u32,
+    pub reload_time: u32,
+    pub damage: i32,
+}
+
+impl Default for Weapon {
+    fn default() -> Self {
+        Weapon {
+            is_firing: false,
+            cooldown: 0,
+            reload_time: 10,
+            damage: 20,
+        }
+    }
+}
+
+/// Damage an actor took during the current update, applied by `DamageHandler`
+#[derive(Debug, Clone, Component)]
+pub struct Damage {
+    pub amount: i32,
+    /// Actor that dealt the last hit
+    pub source: GameId,
+}
+
+/// Keeps a dead player connected until their next spawn
+#[derive(Debug, Clone, Component)]
+pub struct Respawn {
+    /// Updates left until the player spawns again
+    pub delay: u32,
+}
+
+#[derive(Debug, Clone, Component)]
+pub struct Projectile {
+    pub owner: GameId,
+    pub damage: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Component)]
+pub struct Size {
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Size {
+    pub fn paddle() -> Self {
+        Size {
+            width: 15,
+            height: 75,
+        }
+    }
+
+    pub fn rotated(&self) -> Self {
+        Size {
+            width: self.height,
+            height: self.width,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Effect {
+    Enlarge,
+    Shrink,
+    MultiBall,
+    SpeedUp,
+    SlowDown,
+    ReversedControls,
+}
+
+#[derive(Debug, Clone, Component)]
+pub struct Pickup {
+    pub effect: Effect,
+}
+
+/// Player whose paddle touched the ball last
+newtype!(LastHitter(GameId): Debug, Clone, Component);
+
+/// What a player achieved during the current match, kept across respawns
+#[derive(Debug, Clone, Default, Component)]
+pub struct Stats {
+    /// Balls returned in the pong modes or projectiles landed in the others
+    pub hits: u32,
+    pub deaths: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct ActiveEffect {
+    pub effect: Effect,
+    /// Updates left until the effect wears off
+    pub remaining: u32,
+}
+
+#[derive(Debug, Clone, Default, Component)]
+pub struct Effects {
+    pub active: Vec<ActiveEffect>,
+}
+
+impl Effects {
+    pub fn count(&self, effect: Effect) -> usize {
+        self.active.iter().filter(|active| active.effect == effect).count()
+    }
+
+    pub fn contains(&self, effect: Effect) -> bool {
+        self.count(effect) > 0
+    }
+
+    /// How much faster than its actual velocity a ball moves, as every speed up doubles its
+    /// speed and every slow down halves it
+    pub fn speed_factor(&self) -> f64 {
+        let doublings = self.count(Effect::SpeedUp) as i32 - self.count(Effect::SlowDown) as i32;
+        2f64.powi(doublings)
+    }
+}
+
 #[derive(Component)]
 pub struct ToSpawn;
 #[derive(Component)]
@@ -44,6 +171,19 @@ pub struct Player {
     pub send_channel: SendChannel,
     pub inputs: Vec<KeyboardState>,
     pub last_input: u32,
+    /// Whether the client already received its greeting
+    pub greeted: bool,
+    /// Where the view of the player was when they last had a body
+    pub view_center: Option<Vector>,
+    /// Actors the client currently knows about
+    pub visible: HashSet<GameId>,
+    /// How urgently the client needs news about each visible actor
+    pub priorities: PriorityAccumulator<GameId>,
+    /// Sender runs left until the client gets its next world update
+    pub until_update: u32,
+    pub latency: Latency,
+    /// Whether the client still needs to be told about its latest latency
+    pub latency_changed: bool,
 }
 
 impl Player {
@@ -52,10 +192,21 @@ impl Player {
             send_channel,
             inputs: Vec::new(),
             last_input: 0,
+            greeted: false,
+            view_center: None,
+            visible: HashSet::new(),
+            priorities: PriorityAccumulator::default(),
+            until_update: 0,
+            latency: Latency::default(),
+            latency_changed: false,
         }
     }
 }
 
+impl Component for Side {
+    type Storage = DenseVecStorage<Self>;
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct Bounds<T> {
     pub max: T,
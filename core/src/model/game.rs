@@ -1,9 +1,86 @@
 extern crate uuid;
 use self::uuid::Uuid;
+use std::str::FromStr;
 
 pub type Id = Uuid;
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Add, AddAssign, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameMode {
+    Pong,
+    Shooter,
+    /// Top-down mode in which players move freely in both directions
+    Arena,
+    /// Pong with every edge of the field being defended by a player
+    FourPlayer,
+}
+
+impl GameMode {
+    /// Whether players defend sides of the field against balls
+    pub fn is_pong(&self) -> bool {
+        match *self {
+            GameMode::Pong | GameMode::FourPlayer => true,
+            GameMode::Shooter | GameMode::Arena => false,
+        }
+    }
+
+    pub fn sides(&self) -> &'static [Side] {
+        match *self {
+            GameMode::FourPlayer => &[Side::Left, Side::Right, Side::Top, Side::Bottom],
+            _ => &[Side::Left, Side::Right],
+        }
+    }
+
+    /// In the pong modes every team defends one of the `sides`
+    pub fn team_count(&self) -> usize {
+        self.sides().len()
+    }
+}
+
+impl FromStr for GameMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Pong" => Ok(GameMode::Pong),
+            "Shooter" => Ok(GameMode::Shooter),
+            "Arena" => Ok(GameMode::Arena),
+            "FourPlayer" => Ok(GameMode::FourPlayer),
+            _ => Err(format!("Unknown game mode: {}", s)),
+        }
+    }
+}
+
+/// Balls that are kept in play in the pong modes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BallCount(pub usize);
+
+/// Area around a player inside which they get told about other actors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ViewSize {
+    pub width: i32,
+    pub height: i32,
+}
+
+/// Whether projectiles hurt teammates of their shooter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FriendlyFire(pub bool);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Side {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl Side {
+    /// Whether the edge of this side, and with it the paddle defending it, runs horizontally
+    pub fn is_horizontal(&self) -> bool {
+        *self == Side::Top || *self == Side::Bottom
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Add, AddAssign, Hash)]
 pub struct Vector {
     pub x: i32,
     pub y: i32,
@@ -4,7 +4,9 @@ extern crate serde_json;
 use self::serde::ser::Serialize;
 use self::serde_json::Value;
 use model::game::Id;
-use model::comp::Actor;
+use model::comp::{Actor, ActorKind, Team, Name};
+use level::Level;
+use util::{clamp, Time, SeqId};
 use std::fmt::Debug;
 
 #[derive(Debug, Clone, Serialize)]
@@ -13,6 +15,143 @@ pub enum OpCode {
     Spawn,
     Despawn,
     WorldUpdate,
+    Damage,
+    Death,
+    Ping,
+    Latency,
+    Shutdown,
+    Chat,
+    ChatRejected,
+    RoomJoined,
+}
+
+/// Actor as it gets introduced to clients
+#[derive(Debug, Clone, Serialize)]
+pub struct ActorInfo {
+    pub id: Id,
+    pub kind: ActorKind,
+    /// Only players belong to a team
+    pub team: Option<Team>,
+    /// Only players have a name
+    pub name: Option<String>,
+}
+
+impl ActorInfo {
+    pub fn new(actor: &Actor, team: Option<&Team>, name: Option<&Name>) -> Self {
+        ActorInfo {
+            id: actor.id,
+            kind: actor.kind.clone(),
+            team: team.cloned(),
+            name: name.map(|name| name.0.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DamageReport {
+    pub id: Id,
+    pub source: Id,
+    pub amount: i32,
+    pub health: i32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeathReport {
+    pub id: Id,
+    pub killer: Id,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Ping {
+    /// Server time in ms
+    pub sent: Time,
+    pub tick: SeqId,
+}
+
+/// Answer of a client to a `Ping`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Pong {
+    /// Server time of the ping being answered
+    pub sent: Time,
+    /// Client time in ms when the ping arrived
+    pub received: f64,
+}
+
+/// Weight of the newest latency sample
+const LATENCY_SMOOTHING: f64 = 0.2;
+
+/// Round trip time and clock difference to a client, smoothed over several pings
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Latency {
+    /// Round trip time in ms
+    pub rtt: f64,
+    /// Client time minus server time in ms
+    pub clock_offset: f64,
+    pub samples: u32,
+}
+
+impl Latency {
+    /// Adds the measurement of a `pong` that arrived at server time `now`
+    pub fn add_sample(&mut self, pong: &Pong, now: Time) {
+        let rtt = now.saturating_sub(pong.sent) as f64;
+        // The ping is assumed to take half of the round trip
+        let clock_offset = pong.received - (pong.sent as f64 + rtt / 2.0);
+        if self.samples == 0 {
+            self.rtt = rtt;
+            self.clock_offset = clock_offset;
+        } else {
+            self.rtt += (rtt - self.rtt) * LATENCY_SMOOTHING;
+            self.clock_offset += (clock_offset - self.clock_offset) * LATENCY_SMOOTHING;
+        }
+        self.samples += 1;
+    }
+}
+
+/// Everything a client needs to line up its render time with the server ticks
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencyReport {
+    pub rtt: f64,
+    pub clock_offset: f64,
+    /// Ticks per second of the server
+    pub tick_rate: u32,
+    /// World updates per second the client receives
+    pub update_rate: u32,
+}
+
+/// Chat message as it gets passed on to the members of a room
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatMessage {
+    pub room: String,
+    pub sender: Id,
+    pub name: String,
+    pub text: String,
+    /// Server time in ms
+    pub sent: Time,
+}
+
+/// Set once the server is about to stop, so clients can be told why
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Shutdown {
+    pub reason: Option<String>,
+}
+
+/// Limits on the world updates sent to clients
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SendConfig {
+    /// World updates per second the server produces
+    pub updates_per_sec: u32,
+    /// World updates per second a client gets unless it asks for another rate
+    pub default_rate: u32,
+    /// Bytes the actors of a single world update may take up
+    pub byte_budget: usize,
+}
+
+impl SendConfig {
+    /// Sender runs between two world updates for a client that asked for `rate`
+    pub fn interval(&self, rate: Option<u32>) -> u32 {
+        let rate = clamp(rate.unwrap_or(self.default_rate), 1, self.updates_per_sec);
+        self.updates_per_sec / rate
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -24,15 +163,15 @@ where
     pub payload: T,
 }
 impl ServerMsg<Vec<Value>> {
-    pub fn new_greeting(own_id: &Id, actors: &[&Actor]) -> Self {
+    pub fn new_greeting(own_id: &Id, actors: &[ActorInfo], level: &Level) -> Self {
         ServerMsg {
             opcode: OpCode::Greeting,
-            payload: vec![json!(own_id), json!(actors)],
+            payload: vec![json!(own_id), json!(actors), json!(level)],
         }
     }
 }
 impl ServerMsg<Value> {
-    pub fn new_spawn(new_actor: &Actor) -> Self {
+    pub fn new_spawn(new_actor: &ActorInfo) -> Self {
         ServerMsg {
             opcode: OpCode::Spawn,
             payload: json!(new_actor),
@@ -44,12 +183,63 @@ impl ServerMsg<Value> {
             payload: json!(id),
         }
     }
+    pub fn new_damage(report: &DamageReport) -> Self {
+        ServerMsg {
+            opcode: OpCode::Damage,
+            payload: json!(report),
+        }
+    }
+    pub fn new_death(report: &DeathReport) -> Self {
+        ServerMsg {
+            opcode: OpCode::Death,
+            payload: json!(report),
+        }
+    }
+    pub fn new_ping(ping: &Ping) -> Self {
+        ServerMsg {
+            opcode: OpCode::Ping,
+            payload: json!(ping),
+        }
+    }
+    pub fn new_latency(report: &LatencyReport) -> Self {
+        ServerMsg {
+            opcode: OpCode::Latency,
+            payload: json!(report),
+        }
+    }
+    pub fn new_shutdown(reason: &str) -> Self {
+        ServerMsg {
+            opcode: OpCode::Shutdown,
+            payload: json!(reason),
+        }
+    }
+    pub fn new_chat(message: &ChatMessage) -> Self {
+        ServerMsg {
+            opcode: OpCode::Chat,
+            payload: json!(message),
+        }
+    }
+    pub fn new_chat_rejected(reason: &str) -> Self {
+        ServerMsg {
+            opcode: OpCode::ChatRejected,
+            payload: json!(reason),
+        }
+    }
+    pub fn new_room_joined(room: &str) -> Self {
+        ServerMsg {
+            opcode: OpCode::RoomJoined,
+            payload: json!(room),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Hash, PartialEq, Eq)]
 pub enum Command {
     MoveUp,
     MoveDown,
+    MoveLeft,
+    MoveRight,
+    Shoot,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -58,3 +248,94 @@ pub struct ClientMsg {
     pub command: Command,
     pub active: bool,
 }
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Hello {
+    /// Display name, which still needs to be validated
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(tag = "opcode", content = "payload")]
+pub enum ClientRequest {
+    /// First request of every client, which only spawns afterwards
+    Hello(Hello),
+    Input(ClientMsg),
+    /// Preferred team, which gets used from the next spawn on
+    JoinTeam(Team),
+    /// World updates per second the client wants to receive
+    SetSendRate(u32),
+    Pong(Pong),
+    /// Whether the client wants the game to be paused.
+    /// It only pauses once everybody agrees.
+    VotePause(bool),
+    /// Text for everybody in the same chat room
+    Chat(String),
+    /// Chat room to talk in from now on
+    JoinRoom(String),
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn first_latency_sample() {
+        let mut latency = Latency::default();
+        let pong = Pong {
+            sent: 1000,
+            received: 5060.0,
+        };
+        latency.add_sample(&pong, 1100);
+        assert_eq!(100.0, latency.rtt);
+        assert_eq!(4010.0, latency.clock_offset);
+        assert_eq!(1, latency.samples);
+    }
+
+    #[test]
+    fn smooth_latency_samples() {
+        let mut latency = Latency::default();
+        latency.add_sample(
+            &Pong {
+                sent: 1000,
+                received: 1050.0,
+            },
+            1100,
+        );
+        latency.add_sample(
+            &Pong {
+                sent: 2000,
+                received: 2100.0,
+            },
+            2200,
+        );
+        assert!(latency.rtt > 100.0 && latency.rtt < 200.0);
+        assert_eq!(0.0, latency.clock_offset);
+        assert_eq!(2, latency.samples);
+    }
+
+    #[test]
+    fn pong_arriving_early_counts_as_instant() {
+        let mut latency = Latency::default();
+        let pong = Pong {
+            sent: 1000,
+            received: 0.0,
+        };
+        latency.add_sample(&pong, 900);
+        assert_eq!(0.0, latency.rtt);
+    }
+
+    #[test]
+    fn send_interval() {
+        let config = SendConfig {
+            updates_per_sec: 30,
+            default_rate: 10,
+            byte_budget: 1000,
+        };
+        assert_eq!(3, config.interval(None));
+        assert_eq!(1, config.interval(Some(30)));
+        assert_eq!(1, config.interval(Some(100)));
+        assert_eq!(30, config.interval(Some(0)));
+    }
+}
@@ -0,0 +1,130 @@
+use model::game::Id;
+use std::error::Error;
+use std::fmt;
+
+pub const MIN_LENGTH: usize = 2;
+pub const MAX_LENGTH: usize = 16;
+/// Allowed besides letters and digits
+const PUNCTUATION: &[char] = &[' ', '-', '_', '.'];
+const FALLBACK_PREFIX: &str = "Player ";
+const FALLBACK_DIGITS: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NameError {
+    TooShort,
+    TooLong,
+    InvalidCharacter(char),
+    /// Looks like a name made up by the server
+    Reserved,
+}
+
+impl fmt::Display for NameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NameError::TooShort => write!(f, "Name needs at least {} characters", MIN_LENGTH),
+            NameError::TooLong => write!(f, "Name can't be longer than {} characters", MAX_LENGTH),
+            NameError::InvalidCharacter(c) => write!(f, "Name can't contain {:?}", c),
+            NameError::Reserved => write!(f, "Name is reserved for players without one"),
+        }
+    }
+}
+
+impl Error for NameError {
+    fn description(&self) -> &str {
+        match *self {
+            NameError::TooShort => "Name is too short",
+            NameError::TooLong => "Name is too long",
+            NameError::InvalidCharacter(_) => "Name contains an invalid character",
+            NameError::Reserved => "Name is reserved",
+        }
+    }
+}
+
+/// Checks a display name chosen by a player and strips the whitespace around it
+pub fn validate(name: &str) -> Result<String, NameError> {
+    let name = name.trim();
+    if let Some(c) = name.chars().find(|c| {
+        !c.is_alphanumeric() && !PUNCTUATION.contains(c)
+    })
+    {
+        return Err(NameError::InvalidCharacter(c));
+    }
+    let length = name.chars().count();
+    if length < MIN_LENGTH {
+        Err(NameError::TooShort)
+    } else if length > MAX_LENGTH {
+        Err(NameError::TooLong)
+    } else if is_fallback(name) {
+        // Nobody may pass for the player behind another connection
+        Err(NameError::Reserved)
+    } else {
+        Ok(name.to_owned())
+    }
+}
+
+/// Name of a player that didn't choose a valid one
+pub fn fallback(id: &Id) -> String {
+    format!("{}{}", FALLBACK_PREFIX, &id.to_string()[..FALLBACK_DIGITS])
+}
+
+/// Whether `name` looks like it was made up by `fallback`.
+/// Those names belong to a single connection, so they can't identify a player across matches.
+pub fn is_fallback(name: &str) -> bool {
+    name.starts_with(FALLBACK_PREFIX) && {
+        let digits = &name[FALLBACK_PREFIX.len()..];
+        digits.len() == FALLBACK_DIGITS && digits.chars().all(|c| c.is_digit(16))
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn accept_valid_names() {
+        assert_eq!(Ok("Ann".to_owned()), validate("Ann"));
+        assert_eq!(Ok("x_Pro-Gamer.99".to_owned()), validate("x_Pro-Gamer.99"));
+        assert_eq!(Ok("Jürgen Löw".to_owned()), validate("Jürgen Löw"));
+    }
+
+    #[test]
+    fn trim_whitespace() {
+        assert_eq!(Ok("Ann".to_owned()), validate("  Ann\t"));
+        assert_eq!(Err(NameError::TooShort), validate("   "));
+    }
+
+    #[test]
+    fn check_length() {
+        assert_eq!(Err(NameError::TooShort), validate(""));
+        assert_eq!(Err(NameError::TooShort), validate("A"));
+        assert!(validate(&"a".repeat(MAX_LENGTH)).is_ok());
+        assert_eq!(Err(NameError::TooLong), validate(&"a".repeat(MAX_LENGTH + 1)));
+        // Characters count, not bytes
+        assert!(validate(&"ö".repeat(MAX_LENGTH)).is_ok());
+    }
+
+    #[test]
+    fn reject_invalid_characters() {
+        assert_eq!(Err(NameError::InvalidCharacter('<')), validate("<script>"));
+        assert_eq!(Err(NameError::InvalidCharacter('\n')), validate("Ann\nBob"));
+        assert_eq!(Err(NameError::InvalidCharacter('\u{202e}')), validate("Ann\u{202e}"));
+    }
+
+    #[test]
+    fn reject_fallbacks() {
+        let id = Id::new_v4();
+        assert_eq!(Err(NameError::Reserved), validate(&fallback(&id)));
+        assert_eq!(Err(NameError::Reserved), validate(" Player 1a2b "));
+        assert_eq!(Ok("Player One".to_owned()), validate("Player One"));
+    }
+
+    #[test]
+    fn recognize_fallbacks() {
+        assert!(is_fallback(&fallback(&Id::new_v4())));
+        assert!(!is_fallback("Ann"));
+        assert!(!is_fallback("Player One"));
+        assert!(!is_fallback("Player 1a2b3"));
+        assert!(!is_fallback("Player"));
+    }
+}
@@ -0,0 +1,185 @@
+use model::comp::ActorKind;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::f32;
+use std::hash::Hash;
+
+/// Actors with this priority are sent with every update, no matter the byte budget
+pub const ESSENTIAL: f32 = f32::INFINITY;
+/// Players closer than this to the viewer are essential
+pub const NEARBY: f32 = 300.0;
+/// Actors standing still are sent this much less often
+const STATIC_FACTOR: f32 = 0.25;
+
+/// How urgently a client needs to know about an actor during a single update
+pub fn priority(kind: &ActorKind, distance: f32, is_moving: bool) -> f32 {
+    match *kind {
+        ActorKind::Ball => return ESSENTIAL,
+        ActorKind::Player if distance <= NEARBY => return ESSENTIAL,
+        _ => {}
+    }
+    let priority = 1.0 / (1.0 + distance / NEARBY);
+    if is_moving {
+        priority
+    } else {
+        priority * STATIC_FACTOR
+    }
+}
+
+/// Sums up the priorities of actors for a client until they get sent
+#[derive(Debug, Clone)]
+pub struct PriorityAccumulator<Id>
+where
+    Id: Hash + Eq + Clone,
+{
+    priorities: HashMap<Id, f32>,
+}
+
+impl<Id> Default for PriorityAccumulator<Id>
+where
+    Id: Hash + Eq + Clone,
+{
+    fn default() -> Self {
+        PriorityAccumulator { priorities: HashMap::new() }
+    }
+}
+
+impl<Id> PriorityAccumulator<Id>
+where
+    Id: Hash + Eq + Clone,
+{
+    pub fn accumulate(&mut self, id: Id, priority: f32) {
+        *self.priorities.entry(id).or_insert(0.0) += priority;
+    }
+
+    pub fn get(&self, id: &Id) -> Option<f32> {
+        self.priorities.get(id).cloned()
+    }
+
+    /// Forgets every actor that is not `relevant` anymore
+    pub fn retain(&mut self, relevant: &HashSet<Id>) {
+        self.priorities.retain(|id, _| relevant.contains(id));
+    }
+
+    /// Picks the most urgent actors whose serialized `sizes` fit into `budget` bytes
+    /// and starts accumulating their priorities anew.
+    /// Essential actors are always picked, even if they exceed the budget.
+    pub fn select(&mut self, sizes: &HashMap<Id, usize>, budget: usize) -> Vec<Id> {
+        let mut candidates = self.priorities
+            .iter()
+            .filter(|&(id, _)| sizes.contains_key(id))
+            .map(|(id, priority)| (id.clone(), *priority))
+            .collect::<Vec<_>>();
+        candidates.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+
+        let mut used = 0;
+        let mut selected = Vec::new();
+        for (id, priority) in candidates {
+            let size = sizes[&id];
+            if priority == ESSENTIAL || used + size <= budget {
+                used += size;
+                selected.push(id);
+            }
+        }
+        for id in &selected {
+            self.priorities.insert(id.clone(), 0.0);
+        }
+        selected
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn balls_and_nearby_players_are_essential() {
+        assert_eq!(ESSENTIAL, priority(&ActorKind::Ball, 5000.0, false));
+        assert_eq!(ESSENTIAL, priority(&ActorKind::Player, 0.0, false));
+        assert_eq!(ESSENTIAL, priority(&ActorKind::Player, NEARBY, true));
+        assert!(priority(&ActorKind::Player, NEARBY + 1.0, true) < ESSENTIAL);
+    }
+
+    #[test]
+    fn distant_actors_are_less_important() {
+        let close = priority(&ActorKind::Projectile, 100.0, true);
+        let distant = priority(&ActorKind::Projectile, 1000.0, true);
+        assert!(close > distant);
+        assert!(distant > 0.0);
+    }
+
+    #[test]
+    fn static_actors_are_less_important() {
+        let moving = priority(&ActorKind::Pickup, 100.0, true);
+        let still = priority(&ActorKind::Pickup, 100.0, false);
+        assert!(moving > still);
+    }
+
+    #[test]
+    fn select_within_budget() {
+        let mut acc = PriorityAccumulator::default();
+        acc.accumulate(1, 3.0);
+        acc.accumulate(2, 2.0);
+        acc.accumulate(3, 1.0);
+        let sizes = hashmap!(1 => 10, 2 => 10, 3 => 10);
+        assert_eq!(vec![1, 2], acc.select(&sizes, 25));
+    }
+
+    #[test]
+    fn select_skips_actors_that_dont_fit() {
+        let mut acc = PriorityAccumulator::default();
+        acc.accumulate(1, 3.0);
+        acc.accumulate(2, 2.0);
+        acc.accumulate(3, 1.0);
+        let sizes = hashmap!(1 => 10, 2 => 30, 3 => 10);
+        assert_eq!(vec![1, 3], acc.select(&sizes, 25));
+    }
+
+    #[test]
+    fn select_always_includes_essentials() {
+        let mut acc = PriorityAccumulator::default();
+        acc.accumulate(1, ESSENTIAL);
+        acc.accumulate(2, ESSENTIAL);
+        acc.accumulate(3, 5.0);
+        let sizes = hashmap!(1 => 20, 2 => 20, 3 => 1);
+        let mut selected = acc.select(&sizes, 10);
+        selected.sort();
+        assert_eq!(vec![1, 2], selected);
+    }
+
+    #[test]
+    fn select_resets_sent_actors() {
+        let mut acc = PriorityAccumulator::default();
+        acc.accumulate(1, 3.0);
+        acc.accumulate(2, 2.0);
+        let sizes = hashmap!(1 => 10, 2 => 10);
+        acc.select(&sizes, 10);
+        assert_eq!(Some(0.0), acc.get(&1));
+        assert_eq!(Some(2.0), acc.get(&2));
+
+        // Whatever was left out catches up over time
+        acc.accumulate(1, 3.0);
+        acc.accumulate(2, 2.0);
+        assert_eq!(vec![2], acc.select(&sizes, 10));
+    }
+
+    #[test]
+    fn select_ignores_actors_without_state() {
+        let mut acc = PriorityAccumulator::default();
+        acc.accumulate(1, 3.0);
+        acc.accumulate(2, 2.0);
+        let sizes = hashmap!(2 => 10);
+        assert_eq!(vec![2], acc.select(&sizes, 100));
+    }
+
+    #[test]
+    fn retain_relevant() {
+        let mut acc = PriorityAccumulator::default();
+        acc.accumulate(1, 3.0);
+        acc.accumulate(2, 2.0);
+        acc.retain(&hashset!(2));
+        assert_eq!(None, acc.get(&1));
+        assert_eq!(Some(2.0), acc.get(&2));
+    }
+}
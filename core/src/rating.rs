@@ -0,0 +1,409 @@
+use model::comp::Team;
+use results::{MatchResult, PlayerResult};
+use name;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::f64;
+
+/// Rating of a player that never finished a match
+pub const INITIAL: f64 = 1500.0;
+/// Most a rating can change through a single opponent
+const K: f64 = 32.0;
+/// Rating difference at which the stronger player is expected to win ten times as often
+const SCALE: f64 = 400.0;
+
+/// Probability of winning against `opponent`, as in the Elo system
+pub fn expected_score(rating: f64, opponent: f64) -> f64 {
+    1.0 / (1.0 + 10.0_f64.powf((opponent - rating) / SCALE))
+}
+
+/// Skill ratings of players, which are known by their name across connections.
+/// Names aren't authenticated or unique, so whoever picks a name plays with its rating.
+/// That's fine for balancing teams, but means the ratings can't be trusted for anything more.
+#[derive(Debug, Clone, Default)]
+pub struct Ratings {
+    ratings: HashMap<String, f64>,
+}
+
+impl Ratings {
+    /// Replays every match, oldest first
+    pub fn from_matches(matches: &[MatchResult]) -> Self {
+        let mut ratings = Ratings::default();
+        for result in matches {
+            ratings.update(result);
+        }
+        ratings
+    }
+
+    pub fn get(&self, name: &str) -> f64 {
+        self.ratings.get(name).cloned().unwrap_or(INITIAL)
+    }
+
+    /// Rates the players of a finished match.
+    /// Every team plays against every other one and all members share the outcome.
+    pub fn update(&mut self, result: &MatchResult) {
+        let teams = teams(&result.players);
+        if teams.len() < 2 {
+            return;
+        }
+        let team_ratings = teams
+            .iter()
+            .map(|&(ref members, _)| {
+                let sum = members.iter().map(|name| self.get(name)).sum::<f64>();
+                sum / members.len() as f64
+            })
+            .collect::<Vec<_>>();
+
+        let mut changes = Vec::new();
+        for (own, &(ref members, performance)) in teams.iter().enumerate() {
+            let mut change = 0.0;
+            for other in (0..teams.len()).filter(|&other| other != own) {
+                let actual = match performance.partial_cmp(&teams[other].1) {
+                    Some(Ordering::Greater) => 1.0,
+                    Some(Ordering::Less) => 0.0,
+                    _ => 0.5,
+                };
+                change += actual - expected_score(team_ratings[own], team_ratings[other]);
+            }
+            // More opponents don't mean more at stake
+            let change = K * change / (teams.len() - 1) as f64;
+            for name in members {
+                changes.push((name.clone(), change));
+            }
+        }
+        for (name, change) in changes {
+            let rating = self.get(&name) + change;
+            self.ratings.insert(name, rating);
+        }
+    }
+
+    /// The `count` players with the highest rating
+    pub fn top(&self, count: usize) -> Vec<(String, f64)> {
+        let mut ratings = self.ratings
+            .iter()
+            .map(|(name, rating)| (name.clone(), *rating))
+            .collect::<Vec<_>>();
+        ratings.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal).then(
+                a.0.cmp(&b.0),
+            )
+        });
+        ratings.truncate(count);
+        ratings
+    }
+}
+
+/// Names of the players grouped by team, together with how well each team did.
+/// Players without a team form one on their own.
+/// Players without a name of their own can't be recognized later on, so they are left out.
+/// So are players sharing a name within the match, as their results can't be told apart.
+fn teams(players: &[PlayerResult]) -> Vec<(Vec<String>, f64)> {
+    let mut name_counts = HashMap::new();
+    for name in players.iter().filter_map(|player| player.name.as_ref()) {
+        *name_counts.entry(name).or_insert(0) += 1;
+    }
+    let mut by_team: HashMap<Team, (Vec<String>, f64)> = HashMap::new();
+    let mut loners = Vec::new();
+    for player in players {
+        let name = match player.name {
+            Some(ref name) if !name::is_fallback(name) && name_counts[name] == 1 => name.clone(),
+            _ => continue,
+        };
+        match player.team {
+            Some(team) => {
+                let entry = by_team.entry(team).or_insert_with(|| (Vec::new(), 0.0));
+                entry.0.push(name);
+                entry.1 += performance(player);
+            }
+            None => loners.push((vec![name], performance(player))),
+        }
+    }
+    let mut by_team = by_team.into_iter().collect::<Vec<_>>();
+    by_team.sort_by_key(|&(team, _)| team.0);
+    by_team.into_iter().map(|(_, team)| team).chain(loners).collect()
+}
+
+/// Pong modes are decided by the score, the others by hitting more often than getting killed
+fn performance(player: &PlayerResult) -> f64 {
+    match player.score {
+        Some(score) => f64::from(score),
+        None => f64::from(player.hits) - f64::from(player.deaths),
+    }
+}
+
+/// Team a new player should join so that the teams stay as evenly matched as possible.
+/// Only the teams with the fewest `members`, given by their ratings, are considered.
+pub fn balanced_team(rating: f64, members: &[Vec<f64>]) -> usize {
+    let fewest = members.iter().map(Vec::len).min().expect(
+        "Tried to pick a team without any teams",
+    );
+    let mut best = None;
+    for (candidate, _) in members.iter().enumerate().filter(|&(_, team)| {
+        team.len() == fewest
+    })
+    {
+        let spread = spread_after_joining(rating, candidate, members);
+        match best {
+            Some((_, best_spread)) if best_spread <= spread => {}
+            _ => best = Some((candidate, spread)),
+        }
+    }
+    best.unwrap().0
+}
+
+/// Difference between the strongest and weakest team if the player joined `candidate`
+fn spread_after_joining(rating: f64, candidate: usize, members: &[Vec<f64>]) -> f64 {
+    let mut min = f64::INFINITY;
+    let mut max = f64::NEG_INFINITY;
+    for (team, ratings) in members.iter().enumerate() {
+        let mut sum = ratings.iter().sum::<f64>();
+        let mut count = ratings.len();
+        if team == candidate {
+            sum += rating;
+            count += 1;
+        }
+        // Nobody to compare with yet
+        if count == 0 {
+            continue;
+        }
+        let average = sum / count as f64;
+        min = min.min(average);
+        max = max.max(average);
+    }
+    max - min
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use model::game::{Id, GameMode};
+
+    /// Small deterministic generator, so simulations always play out the same
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn next(&mut self) -> f64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % 1_000_000) as f64 / 1_000_000.0
+        }
+
+        fn below(&mut self, max: usize) -> usize {
+            (self.next() * max as f64) as usize
+        }
+    }
+
+    fn player(name: &str, team: u8, score: u32) -> PlayerResult {
+        PlayerResult {
+            id: Id::nil(),
+            name: Some(name.to_owned()),
+            team: Some(Team(team)),
+            score: Some(score),
+            hits: 0,
+            deaths: 0,
+            disconnected: false,
+        }
+    }
+
+    fn result(players: Vec<PlayerResult>) -> MatchResult {
+        MatchResult {
+            started: 0,
+            finished: 1000,
+            mode: GameMode::Pong,
+            players,
+        }
+    }
+
+    fn duel(winner: &str, loser: &str) -> MatchResult {
+        result(vec![player(winner, 0, 5), player(loser, 1, 2)])
+    }
+
+    #[test]
+    fn expected_scores() {
+        assert_eq!(0.5, expected_score(1500.0, 1500.0));
+        assert!(expected_score(1700.0, 1500.0) > 0.7);
+        let sum = expected_score(1700.0, 1500.0) + expected_score(1500.0, 1700.0);
+        assert!((sum - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn unknown_players_start_at_initial() {
+        assert_eq!(INITIAL, Ratings::default().get("Ann"));
+    }
+
+    #[test]
+    fn winner_takes_from_loser() {
+        let mut ratings = Ratings::default();
+        ratings.update(&duel("Ann", "Bob"));
+        assert_eq!(INITIAL + K / 2.0, ratings.get("Ann"));
+        assert_eq!(INITIAL - K / 2.0, ratings.get("Bob"));
+    }
+
+    #[test]
+    fn upsets_move_ratings_further() {
+        let mut ratings = Ratings::default();
+        for _ in 0..5 {
+            ratings.update(&duel("Ann", "Bob"));
+        }
+        let ann = ratings.get("Ann");
+        ratings.update(&duel("Ann", "Bob"));
+        let expected_win = ratings.get("Ann") - ann;
+        ratings.update(&duel("Bob", "Ann"));
+        let upset = ann + expected_win - ratings.get("Ann");
+        assert!(upset > expected_win);
+    }
+
+    #[test]
+    fn draws_between_equals_change_nothing() {
+        let mut ratings = Ratings::default();
+        ratings.update(&result(vec![player("Ann", 0, 3), player("Bob", 1, 3)]));
+        assert_eq!(INITIAL, ratings.get("Ann"));
+        assert_eq!(INITIAL, ratings.get("Bob"));
+    }
+
+    #[test]
+    fn teammates_share_the_outcome() {
+        let mut ratings = Ratings::default();
+        ratings.update(&result(vec![
+            player("Ann", 0, 3),
+            player("Cid", 0, 0),
+            player("Bob", 1, 1),
+        ]));
+        assert!(ratings.get("Ann") > INITIAL);
+        assert_eq!(ratings.get("Ann"), ratings.get("Cid"));
+        assert!(ratings.get("Bob") < INITIAL);
+    }
+
+    #[test]
+    fn shooter_modes_compare_hits_and_deaths() {
+        let mut good = player("Ann", 0, 0);
+        good.score = None;
+        good.hits = 6;
+        good.deaths = 1;
+        let mut bad = player("Bob", 1, 0);
+        bad.score = None;
+        bad.hits = 2;
+        bad.deaths = 4;
+        let mut ratings = Ratings::default();
+        ratings.update(&result(vec![bad, good]));
+        assert!(ratings.get("Ann") > ratings.get("Bob"));
+    }
+
+    #[test]
+    fn ignore_unnamed_players_and_lonely_teams() {
+        let mut unnamed = player("", 1, 0);
+        unnamed.name = None;
+        let mut ratings = Ratings::default();
+        ratings.update(&result(vec![player("Ann", 0, 3), unnamed]));
+        ratings.update(&result(vec![player("Ann", 0, 3), player("Bob", 0, 1)]));
+        assert_eq!(INITIAL, ratings.get("Ann"));
+        assert_eq!(INITIAL, ratings.get("Bob"));
+    }
+
+    #[test]
+    fn ignore_fallback_and_shared_names() {
+        let mut ratings = Ratings::default();
+        let fallback = name::fallback(&Id::new_v4());
+        ratings.update(&duel("Ann", &fallback));
+        assert_eq!(INITIAL, ratings.get("Ann"));
+        assert!(ratings.top(10).is_empty());
+
+        ratings.update(&result(vec![
+            player("Ann", 0, 3),
+            player("Ann", 1, 1),
+            player("Bob", 1, 1),
+        ]));
+        assert_eq!(INITIAL, ratings.get("Ann"));
+        assert_eq!(INITIAL, ratings.get("Bob"));
+    }
+
+    #[test]
+    fn replay_matches() {
+        let matches = [duel("Ann", "Bob"), duel("Ann", "Cid"), duel("Cid", "Bob")];
+        let mut ratings = Ratings::default();
+        for result in &matches {
+            ratings.update(result);
+        }
+        let replayed = Ratings::from_matches(&matches);
+        for name in &["Ann", "Bob", "Cid"] {
+            assert_eq!(ratings.get(name), replayed.get(name));
+        }
+        let top = replayed.top(2);
+        assert_eq!(2, top.len());
+        assert_eq!("Ann", top[0].0);
+    }
+
+    #[test]
+    fn ratings_converge_to_true_skill() {
+        // Players whose real strength is known, but not to the rating system
+        let skills = (0..20).map(|i| 1000.0 + 50.0 * i as f64).collect::<Vec<_>>();
+        let names = (0..skills.len()).map(|i| i.to_string()).collect::<Vec<_>>();
+        let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+        let mut ratings = Ratings::default();
+        for _ in 0..10_000 {
+            let a = rng.below(skills.len());
+            let b = rng.below(skills.len());
+            if a == b {
+                continue;
+            }
+            let a_wins = rng.next() < expected_score(skills[a], skills[b]);
+            let (winner, loser) = if a_wins { (a, b) } else { (b, a) };
+            ratings.update(&duel(&names[winner], &names[loser]));
+        }
+
+        // Pairs of players whose ratings are in the same order as their skills
+        let mut agreeing = 0;
+        let mut pairs = 0;
+        for a in 0..skills.len() {
+            for b in a + 1..skills.len() {
+                pairs += 1;
+                if ratings.get(&names[b]) > ratings.get(&names[a]) {
+                    agreeing += 1;
+                }
+            }
+        }
+        assert!(agreeing as f64 / pairs as f64 > 0.85);
+        let weakest = ratings.get(&names[0]);
+        let strongest = ratings.get(&names[skills.len() - 1]);
+        assert!(strongest - weakest > 500.0);
+    }
+
+    #[test]
+    fn join_smallest_team() {
+        let members = vec![vec![1500.0, 1500.0], vec![1500.0], vec![1500.0, 1400.0]];
+        assert_eq!(1, balanced_team(2000.0, &members));
+    }
+
+    #[test]
+    fn join_team_that_needs_it_most() {
+        let members = vec![vec![1800.0], vec![1200.0]];
+        assert_eq!(1, balanced_team(1600.0, &members));
+        assert_eq!(0, balanced_team(1200.0, &members));
+        assert_eq!(0, balanced_team(1500.0, &[vec![], vec![]]));
+    }
+
+    #[test]
+    fn matchmaking_is_fairer_than_filling_teams_in_turn() {
+        let mut rng = XorShift(0x9e37_79b9_7f4a_7c15);
+        let mut balanced_spread = 0.0;
+        let mut naive_spread = 0.0;
+        for _ in 0..200 {
+            let population = (0..12)
+                .map(|_| 1000.0 + 1000.0 * rng.next())
+                .collect::<Vec<_>>();
+            let mut balanced = vec![Vec::new(), Vec::new()];
+            let mut naive = vec![Vec::new(), Vec::new()];
+            for (i, rating) in population.iter().enumerate() {
+                let team = balanced_team(*rating, &balanced);
+                balanced[team].push(*rating);
+                naive[i % 2].push(*rating);
+            }
+            assert_eq!(balanced[0].len(), balanced[1].len());
+            balanced_spread += spread_after_joining(0.0, usize::max_value(), &balanced);
+            naive_spread += spread_after_joining(0.0, usize::max_value(), &naive);
+        }
+        assert!(balanced_spread < naive_spread / 2.0);
+    }
+}
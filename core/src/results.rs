@@ -0,0 +1,356 @@
+extern crate serde_json;
+
+use model::comp::Team;
+use model::game::{Id, GameMode};
+use name;
+use util::Time;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchResult {
+    /// Server time in ms when the match started
+    pub started: Time,
+    /// Server time in ms when the match ended
+    pub finished: Time,
+    pub mode: GameMode,
+    pub players: Vec<PlayerResult>,
+}
+
+impl MatchResult {
+    /// Length of the match in ms
+    pub fn duration(&self) -> Time {
+        self.finished.saturating_sub(self.started)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerResult {
+    pub id: Id,
+    /// Missing in results recorded before players had names
+    #[serde(default)]
+    pub name: Option<String>,
+    pub team: Option<Team>,
+    /// Only the pong modes keep score
+    pub score: Option<u32>,
+    /// Balls returned in the pong modes or projectiles landed in the others
+    pub hits: u32,
+    pub deaths: u32,
+    /// Whether the player left before the match was over
+    pub disconnected: bool,
+}
+
+/// Everything about the running match that can't be read off the world when it ends
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchRecord {
+    pub started: Time,
+    /// Players that already left
+    pub departed: Vec<PlayerResult>,
+}
+
+impl MatchRecord {
+    pub fn new(started: Time) -> Self {
+        MatchRecord {
+            started,
+            departed: Vec::new(),
+        }
+    }
+}
+
+/// Statistics of everybody who played under the same name.
+/// Ids change with every connection, so the chosen name is all that identifies a player across
+/// matches. Nothing stops somebody else from picking the same name, though.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeaderboardEntry {
+    pub name: String,
+    pub matches: u32,
+    pub score: u32,
+    pub hits: u32,
+    pub deaths: u32,
+    pub disconnects: u32,
+}
+
+#[derive(Debug)]
+pub enum StoreError {
+    Io(io::Error),
+    Parse(serde_json::Error),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StoreError::Io(ref err) => write!(f, "Failed to access results: {}", err),
+            StoreError::Parse(ref err) => write!(f, "Failed to parse results: {}", err),
+        }
+    }
+}
+
+impl Error for StoreError {
+    fn description(&self) -> &str {
+        match *self {
+            StoreError::Io(_) => "Failed to access results",
+            StoreError::Parse(_) => "Failed to parse results",
+        }
+    }
+}
+
+impl From<io::Error> for StoreError {
+    fn from(err: io::Error) -> Self {
+        StoreError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for StoreError {
+    fn from(err: serde_json::Error) -> Self {
+        StoreError::Parse(err)
+    }
+}
+
+/// Somewhere to keep the results of finished matches
+pub trait ResultStore {
+    fn record(&mut self, result: &MatchResult) -> Result<(), StoreError>;
+
+    /// Every recorded match, oldest first
+    fn matches(&self) -> Result<Vec<MatchResult>, StoreError>;
+
+    /// The latest `count` matches, newest first
+    fn recent_matches(&self, count: usize) -> Result<Vec<MatchResult>, StoreError> {
+        let mut matches = self.matches()?;
+        matches.reverse();
+        matches.truncate(count);
+        Ok(matches)
+    }
+
+    /// The `count` players with the highest score over all matches
+    fn leaderboard(&self, count: usize) -> Result<Vec<LeaderboardEntry>, StoreError> {
+        let mut entries = leaderboard(&self.matches()?);
+        entries.truncate(count);
+        Ok(entries)
+    }
+}
+
+/// Keeps every match as a line of JSON in an append-only file
+pub struct JsonLinesStore {
+    path: PathBuf,
+}
+
+impl JsonLinesStore {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        JsonLinesStore { path: path.into() }
+    }
+}
+
+impl ResultStore for JsonLinesStore {
+    fn record(&mut self, result: &MatchResult) -> Result<(), StoreError> {
+        let mut line = serde_json::to_string(result)?;
+        line.push('\n');
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    fn matches(&self) -> Result<Vec<MatchResult>, StoreError> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            // Nothing was recorded yet
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+        let mut matches = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                matches.push(serde_json::from_str(&line)?);
+            }
+        }
+        Ok(matches)
+    }
+}
+
+/// Keeps matches only as long as the process runs
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    matches: Vec<MatchResult>,
+}
+
+impl ResultStore for MemoryStore {
+    fn record(&mut self, result: &MatchResult) -> Result<(), StoreError> {
+        self.matches.push(result.clone());
+        Ok(())
+    }
+
+    fn matches(&self) -> Result<Vec<MatchResult>, StoreError> {
+        Ok(self.matches.clone())
+    }
+}
+
+/// Players without a name of their own are left out, as they can't be told apart across matches
+fn leaderboard(matches: &[MatchResult]) -> Vec<LeaderboardEntry> {
+    let mut entries = HashMap::new();
+    for result in matches {
+        for player in &result.players {
+            let name = match player.name {
+                Some(ref name) if !name::is_fallback(name) => name,
+                _ => continue,
+            };
+            let entry = entries.entry(name.clone()).or_insert_with(|| {
+                LeaderboardEntry {
+                    name: name.clone(),
+                    matches: 0,
+                    score: 0,
+                    hits: 0,
+                    deaths: 0,
+                    disconnects: 0,
+                }
+            });
+            entry.matches += 1;
+            entry.score += player.score.unwrap_or(0);
+            entry.hits += player.hits;
+            entry.deaths += player.deaths;
+            if player.disconnected {
+                entry.disconnects += 1;
+            }
+        }
+    }
+    let mut entries = entries.into_iter().map(|(_, entry)| entry).collect::<Vec<_>>();
+    entries.sort_by(|a, b| {
+        b.score.cmp(&a.score).then(b.hits.cmp(&a.hits)).then(
+            a.name.cmp(&b.name),
+        )
+    });
+    entries
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    fn player(name: &str, score: u32, hits: u32) -> PlayerResult {
+        // Every match is played over a new connection
+        PlayerResult {
+            id: Id::new_v4(),
+            name: Some(name.to_owned()),
+            team: Some(Team(0)),
+            score: Some(score),
+            hits,
+            deaths: 1,
+            disconnected: false,
+        }
+    }
+
+    fn result(started: Time, players: Vec<PlayerResult>) -> MatchResult {
+        MatchResult {
+            started,
+            finished: started + 1000,
+            mode: GameMode::Pong,
+            players,
+        }
+    }
+
+    #[test]
+    fn duration() {
+        assert_eq!(1000, result(500, Vec::new()).duration());
+    }
+
+    #[test]
+    fn json_lines_roundtrip() {
+        let path = env::temp_dir().join(format!("shootr-results-{}.jsonl", Id::new_v4()));
+        let mut store = JsonLinesStore::new(path.clone());
+        assert!(store.matches().unwrap().is_empty());
+
+        let first = result(0, vec![player("Ann", 3, 7)]);
+        let second = result(2000, Vec::new());
+        store.record(&first).unwrap();
+        store.record(&second).unwrap();
+        let matches = store.matches();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(vec![first, second], matches.unwrap());
+    }
+
+    #[test]
+    fn json_lines_reject_garbage() {
+        let path = env::temp_dir().join(format!("shootr-results-{}.jsonl", Id::new_v4()));
+        File::create(&path)
+            .unwrap()
+            .write_all(b"{\"started\": 0\n")
+            .unwrap();
+        let matches = JsonLinesStore::new(path.clone()).matches();
+        fs::remove_file(&path).unwrap();
+        match matches {
+            Err(StoreError::Parse(_)) => {}
+            other => panic!("Expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn recent_matches_first() {
+        let mut store = MemoryStore::default();
+        for started in 0..5 {
+            store.record(&result(started, Vec::new())).unwrap();
+        }
+        let recent = store.recent_matches(2).unwrap();
+        assert_eq!(vec![4, 3], recent.iter().map(|result| result.started).collect::<Vec<_>>());
+        assert_eq!(5, store.recent_matches(10).unwrap().len());
+    }
+
+    #[test]
+    fn leaderboard_sums_up_matches() {
+        let mut left = player("Bob", 4, 1);
+        left.disconnected = true;
+        let mut store = MemoryStore::default();
+        store
+            .record(&result(0, vec![player("Ann", 3, 2), player("Bob", 2, 9)]))
+            .unwrap();
+        store
+            .record(&result(1, vec![player("Ann", 5, 0), left]))
+            .unwrap();
+
+        let board = store.leaderboard(10).unwrap();
+        assert_eq!(2, board.len());
+        assert_eq!(
+            LeaderboardEntry {
+                name: "Ann".to_owned(),
+                matches: 2,
+                score: 8,
+                hits: 2,
+                deaths: 2,
+                disconnects: 0,
+            },
+            board[0]
+        );
+        assert_eq!("Bob", board[1].name);
+        assert_eq!(6, board[1].score);
+        assert_eq!(1, board[1].disconnects);
+        assert_eq!(1, store.leaderboard(1).unwrap().len());
+    }
+
+    #[test]
+    fn leaderboard_breaks_ties_by_hits() {
+        let mut store = MemoryStore::default();
+        store
+            .record(&result(0, vec![player("Sloppy", 3, 1), player("Precise", 3, 5)]))
+            .unwrap();
+        let board = store.leaderboard(10).unwrap();
+        assert_eq!("Precise", board[0].name);
+    }
+
+    #[test]
+    fn leaderboard_skips_players_without_own_name() {
+        let mut unnamed = player("Ann", 3, 1);
+        unnamed.name = None;
+        let fallback = player(&name::fallback(&Id::new_v4()), 3, 1);
+        let mut store = MemoryStore::default();
+        store
+            .record(&result(0, vec![unnamed, fallback, player("Bob", 1, 1)]))
+            .unwrap();
+        let board = store.leaderboard(10).unwrap();
+        assert_eq!(vec!["Bob"], board.iter().map(|entry| &entry.name).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,27 @@
+extern crate libc;
+
+use std::sync::atomic::{AtomicBool, Ordering, ATOMIC_BOOL_INIT};
+
+static REQUESTED: AtomicBool = ATOMIC_BOOL_INIT;
+
+/// Makes SIGINT and SIGTERM request a shutdown instead of killing the process right away
+pub fn handle_signals() {
+    let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    unsafe {
+        libc::signal(libc::SIGINT, handler);
+        libc::signal(libc::SIGTERM, handler);
+    }
+}
+
+extern "C" fn on_signal(_: libc::c_int) {
+    // Only async-signal-safe operations are allowed in here
+    REQUESTED.store(true, Ordering::SeqCst);
+}
+
+pub fn request() {
+    REQUESTED.store(true, Ordering::SeqCst);
+}
+
+pub fn is_requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
@@ -1,69 +1,334 @@
 extern crate specs;
 
-use self::specs::{Fetch, Join, WriteStorage, ReadStorage, System};
+use self::specs::{Fetch, Join, WriteStorage, ReadStorage, System, Entities};
 
-use model::comp::{Pos, Vel, Bounciness, Bounds, Actor};
-use model::game::{Id, Vector};
-use collision::World;
-use util::angle;
+use model::comp::{Pos, Vel, Bounciness, Bounds, Actor, ActorKind, LastHitter, Stats,
+                  ToDespawn, Effects};
+use model::game::{Id, Vector, GameMode};
+use collision::{World, Broadphase, Bounds as CollisionBounds};
+use util::clamp;
+use std::collections::HashMap;
 use std::sync::RwLock;
 
+type PaddleVelocities = HashMap<Id, Vector>;
+
+/// Reflects balls off paddles and walls.
+/// Unlike the other collision systems it doesn't consume `CollisionEvents`: a bounce has to be
+/// resolved while the ball moves, with the sweep's time of impact and contact normal, and the
+/// events of an update only exist after every actor was moved.
 pub struct Bounce;
 impl<'a> System<'a> for Bounce {
     #[allow(type_complexity)]
-    type SystemData = (WriteStorage<'a, Pos>,
+    type SystemData = (Entities<'a>,
+     WriteStorage<'a, Pos>,
      WriteStorage<'a, Vel>,
+     WriteStorage<'a, LastHitter>,
+     WriteStorage<'a, Stats>,
      ReadStorage<'a, Actor>,
      ReadStorage<'a, Bounciness>,
+     ReadStorage<'a, ToDespawn>,
+     ReadStorage<'a, Effects>,
+     Fetch<'a, Bounds<Vel>>,
      Fetch<'a, Bounds<Pos>>,
+     Fetch<'a, GameMode>,
      Fetch<'a, RwLock<World<Id>>>);
 
-    fn run(&mut self, (mut pos, mut vel, actor, bounciness, pos_bounds, world): Self::SystemData) {
-        let world = world.read().unwrap();
-        for (mut pos, mut vel, actor, _) in (&mut pos, &mut vel, &actor, &bounciness).join() {
-            handle_movement(actor, &mut pos, &mut vel, &pos_bounds, &world);
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities,
+             mut pos,
+             mut vel,
+             mut last_hitter,
+             mut stats,
+             actor,
+             bounciness,
+             to_despawn,
+             effects,
+             vel_bounds,
+             pos_bounds,
+             mode,
+             world) = data;
+        let mut world = world.write().unwrap();
+        let paddles = (&actor, &vel)
+            .join()
+            .filter(|&(actor, _)| actor.kind == ActorKind::Player)
+            .map(|(actor, vel)| (actor.id, vel.0.clone()))
+            .collect::<PaddleVelocities>();
+
+        let mut hitters = Vec::new();
+        for (entity, mut pos, mut vel, actor, _) in
+            (&*entities, &mut pos, &mut vel, &actor, &bounciness).join()
+        {
+            if to_despawn.get(entity).is_some() {
+                continue;
+            }
+            let separated_by = separate(actor, &mut pos, &mut vel, &paddles, &mut world);
+            // Speed effects only scale the movement, so they can't pile up in the velocity
+            let speed_factor = effects.get(entity).map_or(1.0, Effects::speed_factor);
+            let moved_by = handle_movement(
+                actor,
+                &mut pos,
+                &mut vel,
+                speed_factor,
+                &paddles,
+                &pos_bounds,
+                &mode,
+                &mut world,
+            );
+            if let Some(paddle) = moved_by.or(separated_by) {
+                hitters.push((entity, paddle));
+            }
+            vel.x = clamp(vel.x, vel_bounds.min.x, vel_bounds.max.x);
+            vel.y = clamp(vel.y, vel_bounds.min.y, vel_bounds.max.y);
+        }
+        let players = (&*entities, &actor, &stats)
+            .join()
+            .map(|(entity, actor, _)| (actor.id, entity))
+            .collect::<HashMap<_, _>>();
+        for (entity, paddle) in hitters {
+            last_hitter.insert(entity, LastHitter(paddle));
+            if let Some(player) = players.get(&paddle) {
+                if let Some(stats) = stats.get_mut(*player) {
+                    stats.hits += 1;
+                }
+            }
+        }
+    }
+}
+
+// Paddles are moved before us, so they might have been pushed into us
+fn separate(
+    actor: &Actor,
+    pos: &mut Pos,
+    vel: &mut Vel,
+    paddles: &PaddleVelocities,
+    world: &mut World<Id>,
+) -> Option<Id> {
+    let mut hitter = None;
+    let mut overlapping = Vec::new();
+    world.query_manifold_id(&actor.id, |other, _| overlapping.push(other.id.clone()));
+    for other in overlapping {
+        // Resolving a previous overlap might already have resolved this one
+        let manifold = {
+            let own_bounds = world.get(&actor.id).unwrap();
+            let other_bounds = world.get(&other).unwrap();
+            own_bounds.manifold(other_bounds)
+        };
+        if let Some(manifold) = manifold {
+            pos.x += manifold.normal.x * manifold.depth;
+            pos.y += manifold.normal.y * manifold.depth;
+            world.place(&actor.id, pos);
+            hitter = respond(actor, vel, &manifold.normal, &other, paddles, world).or(hitter);
         }
     }
+    hitter
 }
 
+// Bouncing actors are not moved by `Physics`, as they could tunnel through
+// thin obstacles when fast enough. Instead, their way is swept and every hit
+// is resolved at the exact point of contact.
 fn handle_movement(
     actor: &Actor,
     pos: &mut Pos,
     vel: &mut Vel,
+    speed_factor: f64,
+    paddles: &PaddleVelocities,
     bounds: &Bounds<Pos>,
-    world: &World<Id>,
-) {
-    world.query_intersects_id(&actor.id, |other| {
-        let own: Vector = Vector { x: pos.x, y: pos.y };
-        let other = Vector {
-            x: other.bounds.x,
-            y: other.bounds.y,
+    mode: &GameMode,
+    world: &mut World<Id>,
+) -> Option<Id> {
+    const MAX_HITS_PER_UPDATE: usize = 4;
+    let mut hitter = None;
+    let mut remaining = speed_factor;
+    for _ in 0..MAX_HITS_PER_UPDATE {
+        let motion = Vector {
+            x: (vel.x as f64 * remaining) as i32,
+            y: (vel.y as f64 * remaining) as i32,
         };
-        if own == other {
-            vel.x = -vel.x;
-        } else {
-            let angle = angle(&own, &other);
-            const SPEED_UP: i32 = 1;
-            let sped_up_x = vel.x.abs() + SPEED_UP;
-            let sped_up_y = vel.y + vel.y / vel.y.abs() * SPEED_UP;
-            if angle > 270.0 || angle < 90.0 {
-                vel.x = -sped_up_x;
-                vel.y = sped_up_y;
-            } else {
-                vel.x = sped_up_x;
-                vel.y = sped_up_y;
+        match world.sweep_id(&actor.id, &motion) {
+            Some((other, hit)) => {
+                // Truncating stops us right before the contact instead of inside the obstacle
+                pos.x += (motion.x as f64 * hit.time) as i32;
+                pos.y += (motion.y as f64 * hit.time) as i32;
+                world.place(&actor.id, pos);
+                hitter = respond(actor, vel, &hit.normal, &other, paddles, world).or(hitter);
+                remaining *= 1.0 - hit.time;
+            }
+            None => {
+                pos.x += motion.x;
+                pos.y += motion.y;
+                break;
             }
         }
-    });
-    let next_x = pos.x + vel.x;
-    let next_y = pos.y + vel.y;
-    if next_x > bounds.max.x || next_x < bounds.min.x {
-        pos.x = 500;
-        pos.y = 500;
-        vel.x = 7;
-        vel.y = 11;
     }
-    if next_y > bounds.max.y || next_y < bounds.min.y {
+
+    // Leaving the field through a defended edge is a goal, which gets counted by `Scoring`
+    pos.x = clamp(pos.x, bounds.min.x, bounds.max.x);
+    if *mode == GameMode::FourPlayer {
+        pos.y = clamp(pos.y, bounds.min.y, bounds.max.y);
+    } else if pos.y > bounds.max.y || pos.y < bounds.min.y {
+        pos.y = if pos.y < bounds.min.y {
+            bounds.min.y
+        } else {
+            bounds.max.y
+        };
         vel.y = -vel.y;
     }
+    world.place(&actor.id, pos);
+    hitter
+}
+
+/// Returns the id of the paddle we got hit by, if any
+fn respond(
+    actor: &Actor,
+    vel: &mut Vel,
+    normal: &Vector,
+    other: &Id,
+    paddles: &PaddleVelocities,
+    world: &World<Id>,
+) -> Option<Id> {
+    let is_approaching = vel.x * normal.x + vel.y * normal.y < 0;
+    if !is_approaching {
+        return None;
+    }
+    match paddles.get(other) {
+        Some(paddle_vel) => {
+            let own_bounds = world.get(&actor.id).unwrap();
+            let paddle_bounds = world.get(other).unwrap();
+            hit_paddle(vel, normal, own_bounds, paddle_bounds, paddle_vel);
+            Some(*other)
+        }
+        None => {
+            if normal.x != 0 {
+                vel.x = -vel.x;
+            }
+            if normal.y != 0 {
+                vel.y = -vel.y;
+            }
+            None
+        }
+    }
+}
+
+// The further away from the center of the paddle we hit it,
+// the steeper we get deflected. A moving paddle additionally
+// lends us some of its own velocity.
+fn hit_paddle(
+    vel: &mut Vel,
+    normal: &Vector,
+    own: &CollisionBounds,
+    paddle: &CollisionBounds,
+    paddle_vel: &Vector,
+) {
+    const SPEED_UP: i32 = 1;
+    const SPIN_DIVISOR: i32 = 4;
+    let is_upright = paddle.height >= paddle.width;
+    let hits_face = (normal.x != 0) == is_upright;
+    if normal.x != 0 {
+        vel.x = normal.x * (vel.x.abs() + SPEED_UP);
+        if hits_face {
+            vel.y = deflection(own.y, paddle.y, paddle.height);
+        }
+        vel.y += paddle_vel.y / SPIN_DIVISOR;
+    } else {
+        vel.y = normal.y * (vel.y.abs() + SPEED_UP);
+        if hits_face {
+            vel.x = deflection(own.x, paddle.x, paddle.width);
+        }
+        vel.x += paddle_vel.x / SPIN_DIVISOR;
+    }
+}
+
+fn deflection(own: i32, paddle_center: i32, paddle_length: i32) -> i32 {
+    const MAX_DEFLECTION: f64 = 12.0;
+    let offset = (own - paddle_center) as f64 / (paddle_length as f64 / 2.0);
+    (offset.max(-1.0).min(1.0) * MAX_DEFLECTION) as i32
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn bounds(x: i32, y: i32, width: i32, height: i32) -> CollisionBounds {
+        CollisionBounds {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    fn vel(x: i32, y: i32) -> Vel {
+        Vel::from(Vector { x, y })
+    }
+
+    /// Ball of velocity `(x, y)` at height `ball_y` hitting the right face of an upright paddle
+    fn hit_upright(x: i32, y: i32, ball_y: i32, paddle_vel: &Vector) -> Vel {
+        let mut vel = vel(x, y);
+        let ball = bounds(30, ball_y, 15, 15);
+        let paddle = bounds(10, 500, 20, 100);
+        hit_paddle(&mut vel, &Vector { x: 1, y: 0 }, &ball, &paddle, paddle_vel);
+        vel
+    }
+
+    #[test]
+    fn deflect_by_distance_to_center() {
+        assert_eq!(0, deflection(500, 500, 100));
+        assert_eq!(6, deflection(525, 500, 100));
+        assert_eq!(-6, deflection(475, 500, 100));
+        assert_eq!(12, deflection(550, 500, 100));
+        assert_eq!(-12, deflection(450, 500, 100));
+        // Balls caught by the corner of the paddle don't get deflected any steeper
+        assert_eq!(12, deflection(580, 500, 100));
+        assert_eq!(-12, deflection(400, 500, 100));
+    }
+
+    #[test]
+    fn return_ball_faster() {
+        let still = Vector { x: 0, y: 0 };
+        let vel = hit_upright(-7, 11, 500, &still);
+        assert_eq!(8, vel.x);
+        // The center sends the ball straight back, regardless of where it came from
+        assert_eq!(0, vel.y);
+        assert_eq!(12, hit_upright(-7, -11, 550, &still).y);
+        assert_eq!(-12, hit_upright(-7, 0, 440, &still).y);
+    }
+
+    #[test]
+    fn deflect_from_sideways_paddle() {
+        let mut vel = vel(3, 7);
+        let ball = bounds(475, 970, 15, 15);
+        let paddle = bounds(500, 990, 100, 20);
+        let still = Vector { x: 0, y: 0 };
+        hit_paddle(&mut vel, &Vector { x: 0, y: -1 }, &ball, &paddle, &still);
+        assert_eq!(-8, vel.y);
+        assert_eq!(-6, vel.x);
+    }
+
+    #[test]
+    fn moving_paddle_lends_spin() {
+        let vel = hit_upright(-7, 0, 500, &Vector { x: 0, y: 20 });
+        assert_eq!(20 / 4, vel.y);
+        let vel = hit_upright(-7, 0, 525, &Vector { x: 0, y: -20 });
+        assert_eq!(6 - 20 / 4, vel.y);
+        // Only the movement along the paddle counts
+        let vel = hit_upright(-7, 0, 500, &Vector { x: 20, y: 0 });
+        assert_eq!(Vel::from(Vector { x: 8, y: 0 }), vel);
+    }
+
+    #[test]
+    fn ignore_balls_moving_away() {
+        let ball = Actor {
+            id: Id::new_v4(),
+            kind: ActorKind::Ball,
+        };
+        let paddle = Id::new_v4();
+        let mut paddles = PaddleVelocities::new();
+        paddles.insert(paddle, Vector { x: 0, y: 0 });
+        let world = World::new(1000, 1000, 100);
+        let mut moving_away = vel(7, 3);
+        let normal = Vector { x: 1, y: 0 };
+        assert_eq!(None, respond(&ball, &mut moving_away, &normal, &paddle, &paddles, &world));
+        assert_eq!(vel(7, 3), moving_away);
+    }
 }
@@ -0,0 +1,22 @@
+extern crate specs;
+use self::specs::{Fetch, FetchMut, System};
+
+use model::game::Id;
+use collision::{World, ContactTracker, CollisionEvent};
+use std::sync::RwLock;
+
+/// Collisions that started or ended during the current update
+pub type CollisionEvents = Vec<CollisionEvent<Id>>;
+
+#[derive(Default)]
+pub struct CollisionDetection {
+    contacts: ContactTracker<Id>,
+}
+impl<'a> System<'a> for CollisionDetection {
+    type SystemData = (FetchMut<'a, CollisionEvents>, Fetch<'a, RwLock<World<Id>>>);
+
+    fn run(&mut self, (mut events, world): Self::SystemData) {
+        let world = world.read().unwrap();
+        *events = self.contacts.update(&*world);
+    }
+}
@@ -0,0 +1,100 @@
+extern crate specs;
+use self::specs::{Join, WriteStorage, ReadStorage, System, Entities, FetchMut};
+
+use model::comp::{Actor, Player, Health, Damage, Respawn, ToSpawn, ToDespawn,
+                  Stats};
+use model::game::Id;
+use model::network::{DamageReport, DeathReport};
+
+#[derive(Debug, Clone)]
+pub enum DamageEvent {
+    Damaged(DamageReport),
+    Died(DeathReport),
+}
+
+/// Damage and deaths that have not been sent to the clients yet
+pub type DamageEvents = Vec<DamageEvent>;
+
+/// Updates a dead player has to wait until they spawn again
+const RESPAWN_DELAY: u32 = 90;
+
+pub struct DamageHandler;
+impl<'a> System<'a> for DamageHandler {
+    #[allow(type_complexity)]
+    type SystemData = (Entities<'a>,
+     ReadStorage<'a, Actor>,
+     ReadStorage<'a, Player>,
+     WriteStorage<'a, Health>,
+     WriteStorage<'a, Damage>,
+     WriteStorage<'a, Respawn>,
+     WriteStorage<'a, Stats>,
+     WriteStorage<'a, ToSpawn>,
+     WriteStorage<'a, ToDespawn>,
+     FetchMut<'a, DamageEvents>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities,
+             actor,
+             player,
+             mut health,
+             mut damage,
+             mut respawn,
+             mut stats,
+             mut to_spawn,
+             mut to_despawn,
+             mut events) = data;
+        let mut hits = Vec::new();
+        for (entity, actor, damage) in (&*entities, &actor, &damage).join() {
+            hits.push((entity, actor.id, damage.clone()));
+        }
+
+        for (entity, id, hit) in hits {
+            damage.remove(entity);
+            if to_despawn.get(entity).is_some() {
+                continue;
+            }
+            let health = match health.get_mut(entity) {
+                Some(health) => health,
+                None => continue,
+            };
+            **health -= hit.amount;
+            events.push(DamageEvent::Damaged(DamageReport {
+                id,
+                source: hit.source,
+                amount: hit.amount,
+                health: **health,
+            }));
+            if **health > 0 {
+                continue;
+            }
+            events.push(DamageEvent::Died(DeathReport {
+                id,
+                killer: hit.source,
+            }));
+            to_despawn.insert(entity, ToDespawn {});
+            if let Some(stats) = stats.get_mut(entity) {
+                stats.deaths += 1;
+            }
+            if player.get(entity).is_some() {
+                respawn.insert(entity, Respawn { delay: RESPAWN_DELAY });
+            }
+        }
+
+        let mut respawned = Vec::new();
+        for (entity, respawn) in (&*entities, &mut respawn).join() {
+            // The timer only starts once `Despawn` removed the body
+            if to_despawn.get(entity).is_some() {
+                continue;
+            }
+            if respawn.delay > 0 {
+                respawn.delay -= 1;
+            } else {
+                respawned.push(entity);
+            }
+        }
+        for entity in respawned {
+            respawn.remove(entity);
+            to_spawn.insert(entity, ToSpawn {});
+        }
+    }
+}
@@ -1,9 +1,9 @@
 extern crate specs;
-use self::specs::{Join, ReadStorage, System, Entities, Fetch};
+use self::specs::{Join, ReadStorage, WriteStorage, System, Entities, Fetch};
 
-use model::comp::{ToDespawn, Actor};
+use model::comp::{ToDespawn, Actor, Respawn, Pos, Vel, Acc, Health, Damage, Weapon};
 use model::game::Id;
-use collision::World;
+use collision::{World, Broadphase};
 use std::sync::RwLock;
 
 pub struct Despawn;
@@ -11,14 +11,47 @@ impl<'a> System<'a> for Despawn {
     #[allow(type_complexity)]
     type SystemData = (Entities<'a>,
      ReadStorage<'a, Actor>,
-     ReadStorage<'a, ToDespawn>,
+     ReadStorage<'a, Respawn>,
+     WriteStorage<'a, ToDespawn>,
+     WriteStorage<'a, Pos>,
+     WriteStorage<'a, Vel>,
+     WriteStorage<'a, Acc>,
+     WriteStorage<'a, Health>,
+     WriteStorage<'a, Damage>,
+     WriteStorage<'a, Weapon>,
      Fetch<'a, RwLock<World<Id>>>);
 
-    fn run(&mut self, (entities, actor, despawn, world): Self::SystemData) {
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities,
+             actor,
+             respawn,
+             mut despawn,
+             mut pos,
+             mut vel,
+             mut acc,
+             mut health,
+             mut damage,
+             mut weapon,
+             world) = data;
         let mut world = world.write().unwrap();
+        let mut bodies = Vec::new();
         for (entity, actor, _) in (&*entities, &actor, &despawn).join() {
-            entities.delete(entity);
             world.remove(&actor.id);
+            if respawn.get(entity).is_some() {
+                // Only the body goes away, the connection stays for the next spawn
+                bodies.push(entity);
+            } else {
+                entities.delete(entity);
+            }
+        }
+        for entity in bodies {
+            despawn.remove(entity);
+            pos.remove(entity);
+            vel.remove(entity);
+            acc.remove(entity);
+            health.remove(entity);
+            damage.remove(entity);
+            weapon.remove(entity);
         }
     }
 }
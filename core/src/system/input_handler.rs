@@ -1,8 +1,8 @@
 extern crate specs;
-use self::specs::{Fetch, Join, WriteStorage, ReadStorage, System};
+use self::specs::{Fetch, Join, WriteStorage, ReadStorage, System, Entities};
 
-use model::comp::{Vel, Player, Actor};
-use model::game::Id;
+use model::comp::{Vel, Acc, Bounds, Player, Actor, Weapon, Effects, Effect};
+use model::game::{Id, GameMode, Side};
 use model::network::{Command, ClientMsg};
 
 use std::sync::{Arc, RwLock};
@@ -12,18 +12,53 @@ type InputMap = Arc<RwLock<HashMap<Id, Vec<ClientMsg>>>>;
 
 pub struct InputHandler;
 impl<'a> System<'a> for InputHandler {
+    #[allow(type_complexity)]
     type SystemData = (Fetch<'a, InputMap>,
+     Fetch<'a, GameMode>,
+     Fetch<'a, Bounds<Acc>>,
+     Entities<'a>,
      WriteStorage<'a, Vel>,
+     WriteStorage<'a, Acc>,
      WriteStorage<'a, Player>,
+     WriteStorage<'a, Weapon>,
+     ReadStorage<'a, Effects>,
+     ReadStorage<'a, Side>,
      ReadStorage<'a, Actor>);
 
-    fn run(&mut self, (inputs, mut acc, mut player, actor): Self::SystemData) {
+    fn run(&mut self, data: Self::SystemData) {
+        let (inputs,
+             mode,
+             acc_bounds,
+             entities,
+             mut vel,
+             mut acc,
+             mut player,
+             mut weapon,
+             effects,
+             side,
+             actor) = data;
         let mut inputs = inputs.write().unwrap();
-        for (mut player, mut vel, actor) in (&mut player, &mut acc, &actor).join() {
+        for (entity, mut player, mut vel, mut acc, actor) in
+            (&*entities, &mut player, &mut vel, &mut acc, &actor).join()
+        {
             if let Some(mut key_states) = inputs.get_mut(&actor.id) {
+                let is_reversed = effects.get(entity).map_or(false, |effects| {
+                    effects.contains(Effect::ReversedControls)
+                });
                 for key_state in key_states.drain(..) {
                     update_player_inputs(&mut player, &key_state);
-                    handle_key_state(player, &mut vel, &key_state);
+                    let key_state = if is_reversed {
+                        reverse(key_state)
+                    } else {
+                        key_state
+                    };
+                    if *mode == GameMode::Arena {
+                        handle_arena_key_state(&mut acc, &acc_bounds, &key_state);
+                    } else {
+                        let is_horizontal = side.get(entity).map_or(false, Side::is_horizontal);
+                        let weapon = weapon.get_mut(entity);
+                        handle_key_state(player, &mut vel, weapon, is_horizontal, &key_state);
+                    }
                 }
                 let bufferlen = 10;
                 let len = player.inputs.len();
@@ -46,21 +81,93 @@ fn update_player_inputs(player: &mut Player, key_state: &ClientMsg) {
     player.last_input = key_state.id;
 }
 
-fn handle_key_state(_: &Player, vel: &mut Vel, key_state: &ClientMsg) {
+fn reverse(mut key_state: ClientMsg) -> ClientMsg {
+    key_state.command = match key_state.command {
+        Command::MoveUp => Command::MoveDown,
+        Command::MoveDown => Command::MoveUp,
+        Command::MoveLeft => Command::MoveRight,
+        Command::MoveRight => Command::MoveLeft,
+        Command::Shoot => Command::Shoot,
+    };
+    key_state
+}
+
+fn handle_key_state(
+    _: &Player,
+    vel: &mut Vel,
+    weapon: Option<&mut Weapon>,
+    is_horizontal: bool,
+    key_state: &ClientMsg,
+) {
+    // Paddles can only move along the edge they defend
     match key_state.command {
-        Command::MoveUp => {
+        Command::MoveUp if !is_horizontal => {
             if key_state.active {
                 vel.y = -25
             } else if vel.y < 0 {
                 vel.y = 0
             }
         }
-        Command::MoveDown => {
+        Command::MoveDown if !is_horizontal => {
             if key_state.active {
                 vel.y = 25
             } else if vel.y > 0 {
                 vel.y = 0
             }
         }
+        Command::MoveLeft if is_horizontal => {
+            if key_state.active {
+                vel.x = -25
+            } else if vel.x < 0 {
+                vel.x = 0
+            }
+        }
+        Command::MoveRight if is_horizontal => {
+            if key_state.active {
+                vel.x = 25
+            } else if vel.x > 0 {
+                vel.x = 0
+            }
+        }
+        Command::Shoot => {
+            if let Some(weapon) = weapon {
+                weapon.is_firing = key_state.active
+            }
+        }
+        _ => {}
+    }
+}
+
+fn handle_arena_key_state(acc: &mut Acc, bounds: &Bounds<Acc>, key_state: &ClientMsg) {
+    match key_state.command {
+        Command::MoveUp => {
+            if key_state.active {
+                acc.y = bounds.min.y
+            } else if acc.y < 0 {
+                acc.y = 0
+            }
+        }
+        Command::MoveDown => {
+            if key_state.active {
+                acc.y = bounds.max.y
+            } else if acc.y > 0 {
+                acc.y = 0
+            }
+        }
+        Command::MoveLeft => {
+            if key_state.active {
+                acc.x = bounds.min.x
+            } else if acc.x < 0 {
+                acc.x = 0
+            }
+        }
+        Command::MoveRight => {
+            if key_state.active {
+                acc.x = bounds.max.x
+            } else if acc.x > 0 {
+                acc.x = 0
+            }
+        }
+        Command::Shoot => {}
     }
 }
@@ -1,9 +1,15 @@
 pub use self::physics::Physics;
-pub use self::sending::Sending;
+pub use self::sending::{Sending, SendRates};
 pub use self::input_handler::InputHandler;
 pub use self::bounce::Bounce;
-pub use self::spawn::Spawn;
+pub use self::spawn::{Spawn, TeamChoices};
 pub use self::despawn::Despawn;
+pub use self::collision_detection::{CollisionDetection, CollisionEvents};
+pub use self::shooting::Shooting;
+pub use self::projectiles::Projectiles;
+pub use self::damage_handler::{DamageHandler, DamageEvent, DamageEvents};
+pub use self::power_ups::PowerUps;
+pub use self::scoring::Scoring;
 
 mod physics;
 mod sending;
@@ -11,3 +17,9 @@ mod input_handler;
 mod bounce;
 mod spawn;
 mod despawn;
+mod collision_detection;
+mod shooting;
+mod projectiles;
+mod damage_handler;
+mod power_ups;
+mod scoring;
@@ -2,10 +2,10 @@ extern crate specs;
 
 use self::specs::{Join, WriteStorage, ReadStorage, System, Fetch, Entities};
 
-use model::comp::{Pos, Vel, Acc, Bounds, Friction, Actor, ToDespawn};
+use model::comp::{Pos, Vel, Acc, Bounds, Friction, Actor, ActorKind, ToDespawn, Bounciness};
 use model::game::Id;
 use util::clamp;
-use collision::World;
+use collision::{World, Broadphase, layer};
 use std::sync::RwLock;
 
 pub struct Physics;
@@ -17,6 +17,7 @@ impl<'a> System<'a> for Physics {
      ReadStorage<'a, Friction>,
      ReadStorage<'a, Actor>,
      ReadStorage<'a, ToDespawn>,
+     ReadStorage<'a, Bounciness>,
      Entities<'a>,
      Fetch<'a, Bounds<Vel>>,
      Fetch<'a, Bounds<Pos>>,
@@ -29,6 +30,7 @@ impl<'a> System<'a> for Physics {
              friction,
              actor,
              to_despawn,
+             bounciness,
              entities,
              vel_bounds,
              pos_bounds,
@@ -38,29 +40,62 @@ impl<'a> System<'a> for Physics {
             vel.y = clamp(vel.y + acc.y, vel_bounds.min.y, vel_bounds.max.y);
         }
 
-        for (mut vel, friction) in (&mut vel, &friction).join() {
-            if vel.y == 0 {
-                continue;
-            }
-            let sign = if vel.y < 0 { 1 } else { -1 };
+        for (mut vel, friction, entity) in (&mut vel, &friction, &*entities).join() {
             use std::ops::Deref;
             let friction = *friction.deref();
-            let new_vel = sign * friction;
-            vel.y += if new_vel > -friction && new_vel < friction {
-                0
-            } else {
-                new_vel
+            // Friction only slows down along the axes nobody is accelerating on
+            let acc = acc.get(entity);
+            if acc.map_or(true, |acc| acc.x == 0) {
+                vel.x = apply_friction(vel.x, friction);
+            }
+            if acc.map_or(true, |acc| acc.y == 0) {
+                vel.y = apply_friction(vel.y, friction);
             }
         }
 
         let mut world = world.write().unwrap();
-        for (mut pos, vel, actor, entity) in (&mut pos, &vel, &actor, &*entities).join() {
-            if to_despawn.get(entity).is_some() {
+        for (mut pos, mut vel, actor, entity) in (&mut pos, &mut vel, &actor, &*entities).join() {
+            // Bouncing actors are moved by `Bounce`
+            if to_despawn.get(entity).is_some() || bounciness.get(entity).is_some() {
                 continue;
             }
             pos.x = clamp(pos.x + vel.x, pos_bounds.min.x, pos_bounds.max.x);
             pos.y = clamp(pos.y + vel.y, pos_bounds.min.y, pos_bounds.max.y);
             world.place(&actor.id, pos);
+            if actor.kind == ActorKind::Player {
+                push_out_of_walls(&actor.id, &mut pos, &mut vel, &mut world);
+            }
         }
     }
 }
+
+fn push_out_of_walls(id: &Id, pos: &mut Pos, vel: &mut Vel, world: &mut World<Id>) {
+    let mut manifolds = Vec::new();
+    world.query_manifold_id(id, |other, manifold| if other.filter.layer & layer::WALL != 0 {
+        manifolds.push(manifold);
+    });
+    if manifolds.is_empty() {
+        return;
+    }
+    for manifold in manifolds {
+        pos.x += manifold.normal.x * manifold.depth;
+        pos.y += manifold.normal.y * manifold.depth;
+        // Running into a wall stops us instead of letting us slide into it again
+        if manifold.normal.x != 0 {
+            vel.x = 0;
+        }
+        if manifold.normal.y != 0 {
+            vel.y = 0;
+        }
+    }
+    world.place(id, pos);
+}
+
+/// Slows `vel` down by `friction` without ever reversing its direction
+fn apply_friction(vel: i32, friction: i32) -> i32 {
+    if vel > 0 {
+        (vel - friction).max(0)
+    } else {
+        (vel + friction).min(0)
+    }
+}
@@ -0,0 +1,301 @@
+extern crate specs;
+extern crate rand;
+use self::specs::{Join, WriteStorage, ReadStorage, System, Entities, Entity, EntitiesRes, Fetch};
+use self::rand::Rng;
+
+use model::comp::{Actor, ActorKind, Pos, Size, Pickup, Effect, ActiveEffect, Effects, LastHitter,
+                  ToSpawn, ToDespawn};
+use model::game::{Vector, Id, GameMode, Side};
+use collision::{World, Broadphase, CollisionEvent, Bounds};
+use system::CollisionEvents;
+use level::Level;
+use std::sync::RwLock;
+use std::collections::HashMap;
+
+const EFFECTS: [Effect; 6] = [
+    Effect::Enlarge,
+    Effect::Shrink,
+    Effect::MultiBall,
+    Effect::SpeedUp,
+    Effect::SlowDown,
+    Effect::ReversedControls,
+];
+/// Updates between two pickups being dropped
+const PICKUP_INTERVAL: u32 = 300;
+const MAX_PICKUPS: usize = 3;
+const PICKUP_SIZE: i32 = 30;
+/// Updates an effect lasts
+const EFFECT_DURATION: u32 = 300;
+const ENLARGEMENT: i32 = 40;
+const SHRINKAGE: i32 = 25;
+const MIN_PADDLE_LENGTH: i32 = 25;
+
+type EntityMap = HashMap<Id, Entity>;
+
+pub struct PowerUps {
+    until_next_pickup: u32,
+}
+
+impl Default for PowerUps {
+    fn default() -> Self {
+        PowerUps { until_next_pickup: PICKUP_INTERVAL }
+    }
+}
+
+impl<'a> System<'a> for PowerUps {
+    #[allow(type_complexity)]
+    type SystemData = (Entities<'a>,
+     WriteStorage<'a, Actor>,
+     WriteStorage<'a, Pos>,
+     WriteStorage<'a, Size>,
+     WriteStorage<'a, Pickup>,
+     WriteStorage<'a, Effects>,
+     ReadStorage<'a, LastHitter>,
+     ReadStorage<'a, Side>,
+     WriteStorage<'a, ToSpawn>,
+     WriteStorage<'a, ToDespawn>,
+     Fetch<'a, GameMode>,
+     Fetch<'a, CollisionEvents>,
+     Fetch<'a, Level>,
+     Fetch<'a, RwLock<World<Id>>>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities,
+             mut actor,
+             mut pos,
+             mut size,
+             mut pickup,
+             mut effects,
+             last_hitter,
+             side,
+             mut to_spawn,
+             mut to_despawn,
+             mode,
+             events,
+             level,
+             world) = data;
+        if !mode.is_pong() {
+            return;
+        }
+
+        if self.until_next_pickup > 0 {
+            self.until_next_pickup -= 1;
+        } else if (&pickup).join().count() < MAX_PICKUPS {
+            self.until_next_pickup = PICKUP_INTERVAL;
+            drop_pickup(
+                &*entities,
+                &mut actor,
+                &mut pos,
+                &mut pickup,
+                &mut to_spawn,
+                &level,
+            );
+        }
+
+        let mut entity_map = EntityMap::new();
+        for (entity, actor) in (&*entities, &actor).join() {
+            entity_map.insert(actor.id, entity);
+        }
+
+        let collected = collected_pickups(&events, &entity_map, &actor, &pickup);
+        for (pickup_entity, ball_entity) in collected {
+            // Two balls might have touched the same pickup at once
+            if to_despawn.get(pickup_entity).is_some() {
+                continue;
+            }
+            to_despawn.insert(pickup_entity, ToDespawn {});
+            let effect = pickup.get(pickup_entity).unwrap().effect;
+            match effect {
+                Effect::Enlarge | Effect::Shrink => {
+                    let hitter = last_hitter.get(ball_entity).and_then(
+                        |hitter| entity_map.get(&hitter.0),
+                    );
+                    if let Some(hitter) = hitter {
+                        add_effect(&mut effects, *hitter, effect);
+                    }
+                }
+                Effect::ReversedControls => {
+                    // Only the opposing teams get confused
+                    let hitter_side = last_hitter
+                        .get(ball_entity)
+                        .and_then(|hitter| entity_map.get(&hitter.0))
+                        .and_then(|hitter| side.get(*hitter));
+                    for (entity, actor) in (&*entities, &actor).join() {
+                        let is_opponent = actor.kind == ActorKind::Player &&
+                            (hitter_side.is_none() || side.get(entity) != hitter_side);
+                        if is_opponent {
+                            add_effect(&mut effects, entity, effect);
+                        }
+                    }
+                }
+                // `Bounce` moves balls according to their active effects
+                Effect::SpeedUp | Effect::SlowDown => add_effect(&mut effects, ball_entity, effect),
+                Effect::MultiBall => {
+                    let ball = entities.create();
+                    actor.insert(
+                        ball,
+                        Actor {
+                            id: Id::new_v4(),
+                            kind: ActorKind::Ball,
+                        },
+                    );
+                    to_spawn.insert(ball, ToSpawn {});
+                    add_effect(&mut effects, ball, effect);
+                }
+            }
+        }
+
+        let mut expired = Vec::new();
+        for (entity, effects) in (&*entities, &mut effects).join() {
+            for active in &mut effects.active {
+                active.remaining = active.remaining.saturating_sub(1);
+                if active.remaining == 0 {
+                    expired.push((entity, active.effect));
+                }
+            }
+            effects.active.retain(|active| active.remaining > 0);
+        }
+        for (entity, effect) in expired {
+            match effect {
+                Effect::MultiBall => {
+                    to_despawn.insert(entity, ToDespawn {});
+                }
+                // Paddle sizes, controls and ball speeds are derived from the active effects
+                Effect::Enlarge | Effect::Shrink | Effect::ReversedControls | Effect::SpeedUp |
+                Effect::SlowDown => {}
+            }
+        }
+
+        let mut world = world.write().unwrap();
+        for (entity, actor, size) in (&*entities, &actor, &mut size).join() {
+            let length = paddle_length(effects.get(entity));
+            let is_horizontal = side.get(entity).map_or(false, Side::is_horizontal);
+            let current = if is_horizontal {
+                size.width
+            } else {
+                size.height
+            };
+            if current != length {
+                if is_horizontal {
+                    size.width = length;
+                } else {
+                    size.height = length;
+                }
+                resize(&mut world, &actor.id, size);
+            }
+        }
+    }
+}
+
+fn drop_pickup(
+    entities: &EntitiesRes,
+    actor: &mut WriteStorage<Actor>,
+    pos: &mut WriteStorage<Pos>,
+    pickup: &mut WriteStorage<Pickup>,
+    to_spawn: &mut WriteStorage<ToSpawn>,
+    level: &Level,
+) {
+    // Keep clear of the paddles at the sides, as far as the level is large enough for that
+    const MARGIN: i32 = 100;
+    const ATTEMPTS: usize = 10;
+    let margin_x = MARGIN.min(level.width / 4).max(PICKUP_SIZE / 2);
+    let margin_y = MARGIN.min(level.height / 4).max(PICKUP_SIZE / 2);
+    if margin_x >= level.width - margin_x || margin_y >= level.height - margin_y {
+        return;
+    }
+    let mut rng = rand::thread_rng();
+    let free_spot = (0..ATTEMPTS)
+        .map(|_| {
+            Bounds {
+                x: rng.gen_range(margin_x, level.width - margin_x),
+                y: rng.gen_range(margin_y, level.height - margin_y),
+                width: PICKUP_SIZE,
+                height: PICKUP_SIZE,
+            }
+        })
+        .find(|bounds| !level.overlaps_wall(bounds));
+    // Try again at the next drop instead of blocking the update on a crowded level
+    let Bounds { x, y, .. } = match free_spot {
+        Some(bounds) => bounds,
+        None => return,
+    };
+    let effect = *rng.choose(&EFFECTS).unwrap();
+
+    let entity = entities.create();
+    actor.insert(
+        entity,
+        Actor {
+            id: Id::new_v4(),
+            kind: ActorKind::Pickup,
+        },
+    );
+    pos.insert(entity, Pos::from(Vector { x, y }));
+    pickup.insert(entity, Pickup { effect });
+    to_spawn.insert(entity, ToSpawn {});
+}
+
+/// Pairs of pickups and the balls that started touching them
+fn collected_pickups(
+    events: &CollisionEvents,
+    entity_map: &EntityMap,
+    actor: &WriteStorage<Actor>,
+    pickup: &WriteStorage<Pickup>,
+) -> Vec<(Entity, Entity)> {
+    let mut collected = Vec::new();
+    for event in events {
+        if let CollisionEvent::Started(ref a, ref b) = *event {
+            for &(pickup_id, ball_id) in &[(a, b), (b, a)] {
+                let pickup_entity = entity_map.get(pickup_id);
+                let ball_entity = entity_map.get(ball_id);
+                if let (Some(&pickup_entity), Some(&ball_entity)) = (pickup_entity, ball_entity) {
+                    let is_ball = actor.get(ball_entity).map_or(false, |actor| {
+                        actor.kind == ActorKind::Ball
+                    });
+                    if is_ball && pickup.get(pickup_entity).is_some() {
+                        collected.push((pickup_entity, ball_entity));
+                    }
+                }
+            }
+        }
+    }
+    collected
+}
+
+fn add_effect(effects: &mut WriteStorage<Effects>, entity: Entity, effect: Effect) {
+    let active = ActiveEffect {
+        effect,
+        remaining: EFFECT_DURATION,
+    };
+    if let Some(effects) = effects.get_mut(entity) {
+        effects.active.push(active);
+        return;
+    }
+    effects.insert(entity, Effects { active: vec![active] });
+}
+
+fn paddle_length(effects: Option<&Effects>) -> i32 {
+    let base = Size::paddle().height;
+    let length = match effects {
+        Some(effects) => {
+            base + effects.count(Effect::Enlarge) as i32 * ENLARGEMENT -
+                effects.count(Effect::Shrink) as i32 * SHRINKAGE
+        }
+        None => base,
+    };
+    length.max(MIN_PADDLE_LENGTH)
+}
+
+fn resize(world: &mut World<Id>, id: &Id, size: &Size) {
+    // Dead players keep their size but have no body to resize
+    let filter = match world.get_filter(id) {
+        Some(filter) => filter.clone(),
+        None => return,
+    };
+    let old = world.remove(id).unwrap();
+    let bounds = Bounds {
+        width: size.width,
+        height: size.height,
+        ..old
+    };
+    world.add_filtered(*id, bounds, filter);
+}
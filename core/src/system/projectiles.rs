@@ -0,0 +1,128 @@
+extern crate specs;
+use self::specs::{Join, WriteStorage, ReadStorage, System, Entities, Fetch};
+
+use model::comp::{Actor, Pos, Vel, Bounds, Health, Damage, Projectile, Team, Stats,
+                  ToDespawn};
+use model::game::{Vector, Id, FriendlyFire};
+use collision::{World, Broadphase, layer};
+use std::sync::RwLock;
+use std::collections::HashMap;
+
+pub struct Projectiles;
+impl<'a> System<'a> for Projectiles {
+    #[allow(type_complexity)]
+    type SystemData = (Entities<'a>,
+     ReadStorage<'a, Actor>,
+     ReadStorage<'a, Projectile>,
+     ReadStorage<'a, Pos>,
+     ReadStorage<'a, Vel>,
+     ReadStorage<'a, Health>,
+     ReadStorage<'a, Team>,
+     WriteStorage<'a, Damage>,
+     WriteStorage<'a, Stats>,
+     WriteStorage<'a, ToDespawn>,
+     Fetch<'a, FriendlyFire>,
+     Fetch<'a, Bounds<Pos>>,
+     Fetch<'a, RwLock<World<Id>>>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities,
+             actor,
+             projectile,
+             pos,
+             vel,
+             health,
+             team,
+             mut damage,
+             mut stats,
+             mut to_despawn,
+             friendly_fire,
+             pos_bounds,
+             world) = data;
+        let mut targets = HashMap::new();
+        for (entity, actor, _) in (&*entities, &actor, &health).join() {
+            targets.insert(actor.id, entity);
+        }
+        let mut teams = HashMap::new();
+        for (actor, team) in (&actor, &team).join() {
+            teams.insert(actor.id, *team);
+        }
+        // Shooters keep their stats while they wait for their respawn
+        let mut shooters = HashMap::new();
+        for (entity, actor, _) in (&*entities, &actor, &stats).join() {
+            shooters.insert(actor.id, entity);
+        }
+
+        let world = world.read().unwrap();
+        let mut used_up = Vec::new();
+        for (entity, projectile, pos, vel) in (&*entities, &projectile, &pos, &vel).join() {
+            if to_despawn.get(entity).is_some() {
+                continue;
+            }
+            // `Physics` already moved the projectile, so check the way it took during this update
+            let from = Vector {
+                x: pos.x - vel.x,
+                y: pos.y - vel.y,
+            };
+            let owner = projectile.owner;
+            let owner_team = teams.get(&owner);
+            // Without friendly fire projectiles fly right through the shooter's teammates
+            let passes_through = |id: &Id| {
+                *id == owner || (!friendly_fire.0 && owner_team.is_some() &&
+                                     teams.get(id) == owner_team)
+            };
+            if let Some((id, _)) = first_hit(&*world, &from, pos, passes_through) {
+                if let Some(target) = targets.get(&id) {
+                    let previous = damage.get(*target).map_or(0, |damage| damage.amount);
+                    let hit = Damage {
+                        amount: previous + projectile.damage,
+                        source: projectile.owner,
+                    };
+                    damage.insert(*target, hit);
+                    if let Some(shooter) = shooters.get(&owner) {
+                        if let Some(stats) = stats.get_mut(*shooter) {
+                            stats.hits += 1;
+                        }
+                    }
+                }
+                used_up.push(entity);
+            } else if is_at_border(pos, &pos_bounds) {
+                used_up.push(entity);
+            }
+        }
+
+        for entity in used_up {
+            to_despawn.insert(entity, ToDespawn {});
+        }
+    }
+}
+
+fn first_hit<F>(
+    world: &World<Id>,
+    from: &Vector,
+    to: &Vector,
+    passes_through: F,
+) -> Option<(Id, f64)>
+where
+    F: Fn(&Id) -> bool,
+{
+    let mut closest: Option<(Id, f64)> = None;
+    world.query_segment(from, to, layer::PLAYER | layer::WALL, |other, hit| {
+        if passes_through(other.id) {
+            return;
+        }
+        let is_closer = match closest {
+            Some((_, distance)) => hit.distance < distance,
+            None => true,
+        };
+        if is_closer {
+            closest = Some((*other.id, hit.distance));
+        }
+    });
+    closest
+}
+
+fn is_at_border(pos: &Pos, bounds: &Bounds<Pos>) -> bool {
+    pos.x <= bounds.min.x || pos.x >= bounds.max.x || pos.y <= bounds.min.y ||
+        pos.y >= bounds.max.y
+}
@@ -0,0 +1,130 @@
+extern crate specs;
+use self::specs::{Join, WriteStorage, ReadStorage, System, Entities, Fetch};
+
+use model::comp::{Actor, ActorKind, Pos, Bounds, Score, LastHitter, ToSpawn, ToDespawn};
+use model::game::{Id, GameMode, BallCount, Side};
+use collision::CollisionEvent;
+use system::CollisionEvents;
+use level::GoalZones;
+use std::collections::HashMap;
+
+pub struct Scoring;
+impl<'a> System<'a> for Scoring {
+    #[allow(type_complexity)]
+    type SystemData = (Entities<'a>,
+     WriteStorage<'a, Actor>,
+     ReadStorage<'a, Pos>,
+     WriteStorage<'a, Score>,
+     ReadStorage<'a, Side>,
+     ReadStorage<'a, LastHitter>,
+     WriteStorage<'a, ToSpawn>,
+     WriteStorage<'a, ToDespawn>,
+     Fetch<'a, Bounds<Pos>>,
+     Fetch<'a, BallCount>,
+     Fetch<'a, GameMode>,
+     Fetch<'a, GoalZones>,
+     Fetch<'a, CollisionEvents>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities,
+             mut actor,
+             pos,
+             mut score,
+             side,
+             last_hitter,
+             mut to_spawn,
+             mut to_despawn,
+             pos_bounds,
+             ball_count,
+             mode,
+             goal_zones,
+             events) = data;
+        if !mode.is_pong() {
+            return;
+        }
+
+        // Actors that touched a goal zone for the first time during this update
+        let mut entered_goals = HashMap::new();
+        for event in events.iter() {
+            if let CollisionEvent::Started(ref a, ref b) = *event {
+                if let Some(side) = goal_zones.get(b) {
+                    entered_goals.insert(*a, *side);
+                } else if let Some(side) = goal_zones.get(a) {
+                    entered_goals.insert(*b, *side);
+                }
+            }
+        }
+
+        let mut goals = Vec::new();
+        let mut balls_in_play = 0;
+        for (entity, actor, pos) in (&*entities, &actor, &pos).join() {
+            if actor.kind != ActorKind::Ball || to_despawn.get(entity).is_some() {
+                continue;
+            }
+            // `Bounce` stops balls that left the field right at its border
+            let conceding_side = entered_goals.get(&actor.id).cloned().or_else(|| {
+                exited_through(pos, &pos_bounds, &mode)
+            });
+            match conceding_side {
+                Some(side) => goals.push((entity, side)),
+                None => balls_in_play += 1,
+            }
+        }
+
+        for (ball, conceding_side) in goals {
+            to_despawn.insert(ball, ToDespawn {});
+            // The team of the last one to touch the ball gets the point, unless it was an own goal.
+            // Then everybody else profits.
+            let hitter = last_hitter.get(ball).map(|hitter| hitter.0);
+            let mut scoring_side = None;
+            for (actor, side) in (&actor, &side).join() {
+                if Some(actor.id) == hitter && *side != conceding_side {
+                    scoring_side = Some(*side);
+                }
+            }
+            let mut scorers = Vec::new();
+            for (entity, side, _) in (&*entities, &side, &score).join() {
+                let scores = match scoring_side {
+                    Some(scoring_side) => *side == scoring_side,
+                    None => *side != conceding_side,
+                };
+                if scores {
+                    scorers.push(entity);
+                }
+            }
+            for scorer in scorers {
+                **score.get_mut(scorer).unwrap() += 1;
+            }
+        }
+
+        for _ in balls_in_play..ball_count.0 {
+            let ball = entities.create();
+            actor.insert(
+                ball,
+                Actor {
+                    id: Id::new_v4(),
+                    kind: ActorKind::Ball,
+                },
+            );
+            to_spawn.insert(ball, ToSpawn {});
+        }
+    }
+}
+
+/// Side of the field that `pos` left through, if it did so through a defended edge
+fn exited_through(pos: &Pos, bounds: &Bounds<Pos>, mode: &GameMode) -> Option<Side> {
+    if pos.x <= bounds.min.x {
+        Some(Side::Left)
+    } else if pos.x >= bounds.max.x {
+        Some(Side::Right)
+    } else if *mode != GameMode::FourPlayer {
+        // The other edges are walls
+        None
+    } else if pos.y <= bounds.min.y {
+        Some(Side::Top)
+    } else if pos.y >= bounds.max.y {
+        Some(Side::Bottom)
+    } else {
+        None
+    }
+}
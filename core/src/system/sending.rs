@@ -4,42 +4,140 @@ extern crate serde;
 extern crate serde_json;
 extern crate websocket_server;
 
-use self::specs::{Join, ReadStorage, WriteStorage, System, Entities, EntitiesRes, Fetch};
+use self::specs::{Join, ReadStorage, WriteStorage, System, Entities, EntitiesRes, Fetch,
+                  FetchMut};
 use self::futures::{Future, Sink};
 use self::websocket_server::Message;
 use self::serde::ser::Serialize;
+use self::serde_json::Value;
 
-use model::comp::{Pos, Vel, ToSpawn, ToDespawn, Player as PlayerComp, Actor};
-use model::network::{ServerMsg as ClientMessage, OpCode};
-use util::SeqId;
+use model::comp::{Pos, Vel, Health, Score, Size, Team, Name, ToSpawn, ToDespawn,
+                  Player as PlayerComp, Actor};
+use model::network::{ServerMsg as ClientMessage, OpCode, ActorInfo, SendConfig, Ping,
+                     LatencyReport, Shutdown};
+use model::game::{Id, Vector, ViewSize};
+use system::{DamageEvent, DamageEvents};
+use collision::{World, Broadphase, Bounds as CollisionBounds};
+use level::Level;
+use clock::GameClock;
+use priority::{priority, ESSENTIAL};
+use util::{SeqId, timestamp};
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
 use std::fmt::Debug;
+use std::f32;
+
+/// World updates per second that clients asked for
+pub type SendRates = Arc<RwLock<HashMap<Id, u32>>>;
+
+/// Actors with a body that clients may be told about
+type PresentActors = HashMap<Id, ActorInfo>;
+type SerializedActors = HashMap<Id, HashMap<&'static str, Value>>;
+
+/// Sender runs between two pings to every client
+const PING_INTERVAL: SeqId = 60;
 
 pub struct Sending;
+
 impl<'a> System<'a> for Sending {
     #[allow(type_complexity)]
     type SystemData = (ReadStorage<'a, Pos>,
      ReadStorage<'a, Vel>,
-     ReadStorage<'a, PlayerComp>,
+     ReadStorage<'a, Health>,
+     ReadStorage<'a, Score>,
+     ReadStorage<'a, Size>,
+     WriteStorage<'a, PlayerComp>,
      ReadStorage<'a, Actor>,
+     ReadStorage<'a, Team>,
+     ReadStorage<'a, Name>,
      WriteStorage<'a, ToSpawn>,
      ReadStorage<'a, ToDespawn>,
-     Fetch<'a, SeqId>,
+     Fetch<'a, GameClock>,
+     FetchMut<'a, DamageEvents>,
+     Fetch<'a, Level>,
+     Fetch<'a, ViewSize>,
+     Fetch<'a, SendConfig>,
+     Fetch<'a, SendRates>,
+     Fetch<'a, Shutdown>,
+     Fetch<'a, RwLock<World<Id>>>,
      Entities<'a>);
 
     fn run(&mut self, data: Self::SystemData) {
-        let (pos, vel, player, actor, mut connect, disconnect, curr_tick, entities) = data;
+        let (pos,
+             vel,
+             health,
+             score,
+             size,
+             mut player,
+             actor,
+             team,
+             name,
+             mut connect,
+             disconnect,
+             clock,
+             mut damage_events,
+             level,
+             view_size,
+             send_config,
+             send_rates,
+             shutdown,
+             world,
+             entities) = data;
+        let world = world.read().unwrap();
+        let curr_tick = clock.tick();
 
-        handle_new_connections(&player, *curr_tick, &*entities, &actor, &mut connect);
-        handle_disconnects(&player, *curr_tick, &actor, &disconnect);
+        // Actors without a body can't be seen and leaving ones are already gone for the clients
+        let mut present = PresentActors::new();
+        for (entity, actor, _) in (&*entities, &actor, &pos).join() {
+            if disconnect.get(entity).is_none() && world.get(&actor.id).is_some() {
+                let info = ActorInfo::new(actor, team.get(entity), name.get(entity));
+                present.insert(actor.id, info);
+            }
+        }
+        for (player, pos) in (&mut player, &pos).join() {
+            player.view_center = Some(pos.0.clone());
+        }
+
+        handle_new_connections(
+            &mut player,
+            curr_tick,
+            &*entities,
+            &actor,
+            &present,
+            &world,
+            &view_size,
+            &level,
+            &mut connect,
+        );
+        // Dying actors are about to leave the view, so check against what clients already know
+        send_damage_events(&player, curr_tick, &actor, &mut damage_events);
+        update_interest(&mut player, curr_tick, &actor, &present, &world, &view_size);
+        if clock.send_tick() % PING_INTERVAL == 0 {
+            send_pings(&player, curr_tick, &actor);
+        }
+        send_latency_reports(&mut player, curr_tick, &actor, &send_config, &send_rates);
 
-        send_world_updates(&player, *curr_tick, &actor, &pos, &vel);
+        prioritize(&mut player, &actor, &pos, &vel);
+        let serialized_actors = serialize_actors(&actor, &pos, &vel, &health, &score, &size);
+        send_world_updates(
+            &mut player,
+            &clock,
+            &actor,
+            &serialized_actors,
+            &send_config,
+            &send_rates,
+        );
+        if let Some(ref reason) = shutdown.reason {
+            let msg = ClientMessage::new_shutdown(reason);
+            for (player, actor) in (&player, &actor).join() {
+                send(&actor.id, player, curr_tick, &msg);
+            }
+        }
     }
 }
 
-
-fn send<T>(player: &PlayerComp, curr_tick: SeqId, msg: &ClientMessage<T>)
+fn send<T>(id: &Id, player: &PlayerComp, curr_tick: SeqId, msg: &ClientMessage<T>)
 where
     T: Serialize + Debug,
 {
@@ -49,88 +147,261 @@ where
     let tick = format!(",\"tick\":{}", curr_tick);
     let json_end_pos = msg.len() - 1;
     msg.insert_str(json_end_pos, &tick);
-    send_channel.send(Message::Text(msg)).wait().expect(
-        "Failed to send message",
-    );
+    // The client might be gone already, which is no reason to stop the server
+    if send_channel.send(Message::Text(msg)).wait().is_err() {
+        println!("Client {}: Failed to send message", id);
+    }
 }
 
 
 fn handle_new_connections(
-    player: &ReadStorage<PlayerComp>,
+    player: &mut WriteStorage<PlayerComp>,
     curr_tick: SeqId,
     entities: &EntitiesRes,
     actor: &ReadStorage<Actor>,
+    present: &PresentActors,
+    world: &World<Id>,
+    view_size: &ViewSize,
+    level: &Level,
     spawn: &mut WriteStorage<ToSpawn>,
 ) {
-    let mut new_connections = Vec::new();
-    for (entity, actor, _) in (entities, actor, &mut *spawn).join() {
-        new_connections.push((entity, actor.clone()));
-    }
-
-    let mut actors = Vec::new();
-    for actor in (actor).join() {
-        actors.push(actor);
-    }
-    for new_connection in new_connections {
-        let (new_entity, new_actor) = new_connection;
-        spawn.remove(new_entity);
-        let greeting_msg = ClientMessage::new_greeting(&new_actor.id, &actors);
-        let other_spawn_msg = ClientMessage::new_spawn(&new_actor);
-        for (player, entity) in (player, entities).join() {
-            if entity == new_entity {
-                send(player, curr_tick, &greeting_msg);
-            } else {
-                send(player, curr_tick, &other_spawn_msg);
-            }
+    // `Spawn` keeps trying until it gave the actor a body
+    let mut spawned = Vec::new();
+    for (entity, actor, _) in (entities, actor, &*spawn).join() {
+        if present.contains_key(&actor.id) {
+            spawned.push(entity);
         }
     }
+    for entity in spawned {
+        spawn.remove(entity);
+    }
+
+    // Everybody else learns about new actors as soon as they come into view
+    for (player, actor) in (&mut *player, actor).join() {
+        if player.greeted || !present.contains_key(&actor.id) {
+            continue;
+        }
+        let in_view = actors_in_view(player, world, view_size, present);
+        let actors = in_view.iter().map(|id| present[id].clone()).collect::<Vec<_>>();
+        let greeting_msg = ClientMessage::new_greeting(&actor.id, &actors, level);
+        send(&actor.id, player, curr_tick, &greeting_msg);
+        player.greeted = true;
+        player.visible = in_view;
+    }
 }
 
-fn handle_disconnects(
-    player: &ReadStorage<PlayerComp>,
+/// Announces actors that entered or left the view of each player
+fn update_interest(
+    player: &mut WriteStorage<PlayerComp>,
     curr_tick: SeqId,
     actor: &ReadStorage<Actor>,
-    disconnect: &ReadStorage<ToDespawn>,
+    present: &PresentActors,
+    world: &World<Id>,
+    view_size: &ViewSize,
 ) {
-    for (actor, _) in (actor, disconnect).join() {
-        let msg = ClientMessage::new_despawn(&actor.id);
-        for player in (player).join() {
-            send(player, curr_tick, &msg);
+    for (player, actor) in (&mut *player, actor).join() {
+        if !player.greeted {
+            continue;
+        }
+        let in_view = actors_in_view(player, world, view_size, present);
+        for id in in_view.difference(&player.visible) {
+            send(&actor.id, player, curr_tick, &ClientMessage::new_spawn(&present[id]));
         }
+        for id in player.visible.difference(&in_view) {
+            send(&actor.id, player, curr_tick, &ClientMessage::new_despawn(id));
+        }
+        player.visible = in_view;
     }
+}
 
+fn actors_in_view(
+    player: &PlayerComp,
+    world: &World<Id>,
+    view_size: &ViewSize,
+    present: &PresentActors,
+) -> HashSet<Id> {
+    let mut in_view = HashSet::new();
+    if let Some(ref center) = player.view_center {
+        let view = CollisionBounds {
+            x: center.x,
+            y: center.y,
+            width: view_size.width,
+            height: view_size.height,
+        };
+        world.query_intersects_other(&view, |other| if present.contains_key(other.id) {
+            in_view.insert(*other.id);
+        });
+    }
+    in_view
 }
 
-fn send_world_updates(
-    player: &ReadStorage<PlayerComp>,
+/// Only players that know about the damaged actor hear about it
+fn send_damage_events(
+    player: &WriteStorage<PlayerComp>,
     curr_tick: SeqId,
     actor: &ReadStorage<Actor>,
+    events: &mut DamageEvents,
+) {
+    for event in events.drain(..) {
+        let (msg, target) = match event {
+            DamageEvent::Damaged(ref report) => (ClientMessage::new_damage(report), report.id),
+            DamageEvent::Died(ref report) => (ClientMessage::new_death(report), report.id),
+        };
+        for (player, actor) in (player, actor).join() {
+            if player.visible.contains(&target) {
+                send(&actor.id, player, curr_tick, &msg);
+            }
+        }
+    }
+}
+
+fn send_pings(player: &WriteStorage<PlayerComp>, curr_tick: SeqId, actor: &ReadStorage<Actor>) {
+    let ping = Ping {
+        sent: timestamp(),
+        tick: curr_tick,
+    };
+    let msg = ClientMessage::new_ping(&ping);
+    for (player, actor) in (player, actor).join() {
+        send(&actor.id, player, curr_tick, &msg);
+    }
+}
+
+fn send_latency_reports(
+    player: &mut WriteStorage<PlayerComp>,
+    curr_tick: SeqId,
+    actor: &ReadStorage<Actor>,
+    config: &SendConfig,
+    rates: &SendRates,
+) {
+    let rates = rates.read().unwrap();
+    for (player, actor) in (&mut *player, actor).join() {
+        if !player.latency_changed {
+            continue;
+        }
+        player.latency_changed = false;
+        let interval = config.interval(rates.get(&actor.id).cloned());
+        let report = LatencyReport {
+            rtt: player.latency.rtt,
+            clock_offset: player.latency.clock_offset,
+            tick_rate: config.updates_per_sec,
+            update_rate: config.updates_per_sec / interval.max(1),
+        };
+        send(&actor.id, player, curr_tick, &ClientMessage::new_latency(&report));
+    }
+}
+
+fn prioritize(
+    player: &mut WriteStorage<PlayerComp>,
+    actor: &ReadStorage<Actor>,
     pos: &ReadStorage<Pos>,
     vel: &ReadStorage<Vel>,
 ) {
-    let mut serialized_actors = HashMap::new();
-    for actor in (actor).join() {
-        serialized_actors.insert(actor.id, HashMap::new());
+    let mut details = HashMap::new();
+    for (actor, pos, vel) in (actor, pos, vel).join() {
+        let is_moving = vel.x != 0 || vel.y != 0;
+        details.insert(actor.id, (actor.kind.clone(), pos.0.clone(), is_moving));
+    }
+    for player in (&mut *player).join() {
+        for id in &player.visible {
+            if let Some(&(ref kind, ref pos, is_moving)) = details.get(id) {
+                let distance = player.view_center.as_ref().map_or(
+                    f32::INFINITY,
+                    |center| distance(center, pos),
+                );
+                // Clients need a first state of actors that just came into view
+                let priority = if player.priorities.get(id).is_none() {
+                    ESSENTIAL
+                } else {
+                    priority(kind, distance, is_moving)
+                };
+                player.priorities.accumulate(*id, priority);
+            }
+        }
+        player.priorities.retain(&player.visible);
     }
+}
+
+fn distance(a: &Vector, b: &Vector) -> f32 {
+    let d_x = (a.x - b.x) as f32;
+    let d_y = (a.y - b.y) as f32;
+    (d_x * d_x + d_y * d_y).sqrt()
+}
 
+fn serialize_actors(
+    actor: &ReadStorage<Actor>,
+    pos: &ReadStorage<Pos>,
+    vel: &ReadStorage<Vel>,
+    health: &ReadStorage<Health>,
+    score: &ReadStorage<Score>,
+    size: &ReadStorage<Size>,
+) -> SerializedActors {
+    let mut serialized_actors = HashMap::new();
     for (pos, vel, actor) in (pos, vel, actor).join() {
-        let mut actor = serialized_actors.get_mut(&actor.id).unwrap();
-        actor.insert("pos", json!(pos));
-        actor.insert("vel", json!(vel));
+        let mut serialized = HashMap::new();
+        serialized.insert("pos", json!(pos));
+        serialized.insert("vel", json!(vel));
+        serialized_actors.insert(actor.id, serialized);
     }
+    for (health, actor) in (health, actor).join() {
+        if let Some(serialized) = serialized_actors.get_mut(&actor.id) {
+            serialized.insert("health", json!(health));
+        }
+    }
+    for (score, actor) in (score, actor).join() {
+        if let Some(serialized) = serialized_actors.get_mut(&actor.id) {
+            serialized.insert("score", json!(score));
+        }
+    }
+    for (size, actor) in (size, actor).join() {
+        if let Some(serialized) = serialized_actors.get_mut(&actor.id) {
+            serialized.insert("size", json!(size));
+        }
+    }
+    serialized_actors
+}
+
+fn send_world_updates(
+    player: &mut WriteStorage<PlayerComp>,
+    clock: &GameClock,
+    actor: &ReadStorage<Actor>,
+    serialized_actors: &SerializedActors,
+    config: &SendConfig,
+    rates: &SendRates,
+) {
+    // A quoted UUID as key plus the separators around it
+    const KEY_SIZE: usize = 40;
+    let sizes = serialized_actors
+        .iter()
+        .map(|(id, serialized)| (*id, json!(serialized).to_string().len() + KEY_SIZE))
+        .collect::<HashMap<_, _>>();
+
+    let paused = json!(clock.is_paused());
+    let rates = rates.read().unwrap();
+    for (player, actor) in (&mut *player, actor).join() {
+        if player.until_update > 0 {
+            player.until_update -= 1;
+            continue;
+        }
+        let rate = rates.get(&actor.id).cloned();
+        player.until_update = config.interval(rate).saturating_sub(1);
 
-    let json_actors = json!(serialized_actors);
-    for player in (player).join() {
+        let selected = player.priorities.select(&sizes, config.byte_budget);
+        let actors = selected
+            .iter()
+            .map(|id| (id, &serialized_actors[id]))
+            .collect::<HashMap<_, _>>();
+        let json_actors = json!(actors);
         let last_input = json!(player.last_input);
         let payload =
             hashmap!(
             "last_input" => &last_input,
-            "actors" => &json_actors
+            "actors" => &json_actors,
+            "paused" => &paused
         );
         let world_state = ClientMessage {
             opcode: OpCode::WorldUpdate,
             payload: &payload,
         };
-        send(player, curr_tick, &world_state);
+        send(&actor.id, player, clock.tick(), &world_state);
     }
 }
@@ -0,0 +1,70 @@
+extern crate specs;
+use self::specs::{Join, WriteStorage, System, Entities, Fetch};
+
+use model::comp::{Actor, ActorKind, Pos, Vel, Bounds, Weapon, Projectile, ToSpawn};
+use model::game::{Vector, Id};
+
+pub struct Shooting;
+impl<'a> System<'a> for Shooting {
+    #[allow(type_complexity)]
+    type SystemData = (Entities<'a>,
+     WriteStorage<'a, Actor>,
+     WriteStorage<'a, Weapon>,
+     WriteStorage<'a, Pos>,
+     WriteStorage<'a, Vel>,
+     WriteStorage<'a, Projectile>,
+     WriteStorage<'a, ToSpawn>,
+     Fetch<'a, Bounds<Pos>>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities,
+             mut actor,
+             mut weapon,
+             mut pos,
+             mut vel,
+             mut projectile,
+             mut to_spawn,
+             pos_bounds) = data;
+        let center = (pos_bounds.min.x + pos_bounds.max.x) / 2;
+        let mut shots = Vec::new();
+        for (actor, weapon, pos) in (&actor, &mut weapon, &pos).join() {
+            if weapon.cooldown > 0 {
+                weapon.cooldown -= 1;
+                continue;
+            }
+            if weapon.is_firing {
+                weapon.cooldown = weapon.reload_time;
+                // Players can't aim yet, so they always shoot at the opposing side
+                let direction = if pos.x < center { 1 } else { -1 };
+                let shot = Projectile {
+                    owner: actor.id,
+                    damage: weapon.damage,
+                };
+                shots.push((shot, pos.clone(), direction));
+            }
+        }
+
+        for (shot, shooter_pos, direction) in shots {
+            let entity = entities.create();
+            actor.insert(
+                entity,
+                Actor {
+                    id: Id::new_v4(),
+                    kind: ActorKind::Projectile,
+                },
+            );
+            pos.insert(entity, shooter_pos);
+            vel.insert(
+                entity,
+                Vel::from(Vector {
+                    x: direction * PROJECTILE_SPEED,
+                    y: 0,
+                }),
+            );
+            projectile.insert(entity, shot);
+            to_spawn.insert(entity, ToSpawn {});
+        }
+    }
+}
+
+const PROJECTILE_SPEED: i32 = 30;
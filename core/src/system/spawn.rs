@@ -1,10 +1,23 @@
 extern crate specs;
-use self::specs::{Join, WriteStorage, ReadStorage, System, Entities, Entity, Fetch};
+extern crate rand;
+use self::specs::{Join, WriteStorage, ReadStorage, System, Entities, Entity, EntitiesRes, Fetch};
+use self::rand::Rng;
 
-use model::comp::{Actor, ActorKind, Pos, Vel, Acc, ToSpawn, Bounciness};
-use model::game::{Vector, Id};
-use collision::{World, Bounds};
-use std::sync::RwLock;
+use model::comp::{Actor, ActorKind, Pos, Vel, Acc, Size, ToSpawn, Bounciness, Friction,
+                  Health, Score, Weapon, Team, Name};
+use model::game::{Vector, Id, GameMode, Side};
+use collision::{World, Broadphase, Bounds, Filter, layer};
+use level::Level;
+use rating::{self, Ratings};
+use std::sync::{Arc, RwLock};
+use std::collections::HashMap;
+
+/// Teams that clients asked to join
+pub type TeamChoices = Arc<RwLock<HashMap<Id, Team>>>;
+
+/// Distance between the rows of paddles of a team sharing a side
+const ROW_SPACING: i32 = 40;
+const MAX_ROWS: usize = 3;
 
 pub struct Spawn;
 impl<'a> System<'a> for Spawn {
@@ -12,77 +25,334 @@ impl<'a> System<'a> for Spawn {
     type SystemData = (Entities<'a>,
      ReadStorage<'a, ToSpawn>,
      ReadStorage<'a, Actor>,
+     ReadStorage<'a, Name>,
 
      WriteStorage<'a, Pos>,
      WriteStorage<'a, Vel>,
      WriteStorage<'a, Acc>,
+     WriteStorage<'a, Size>,
      WriteStorage<'a, Bounciness>,
+     WriteStorage<'a, Friction>,
+     WriteStorage<'a, Health>,
+     WriteStorage<'a, Score>,
+     WriteStorage<'a, Side>,
+     WriteStorage<'a, Team>,
+     WriteStorage<'a, Weapon>,
 
+     Fetch<'a, GameMode>,
+     Fetch<'a, TeamChoices>,
+     Fetch<'a, Ratings>,
+     Fetch<'a, Friction>,
+     Fetch<'a, Level>,
      Fetch<'a, RwLock<World<Id>>>);
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, to_spawn, actor, mut pos, mut vel, mut acc, mut bounciness, world) = data;
+        let (entities,
+             to_spawn,
+             actor,
+             name,
+             mut pos,
+             mut vel,
+             mut acc,
+             mut size,
+             mut bounciness,
+             mut friction,
+             mut health,
+             mut score,
+             mut side,
+             mut team,
+             mut weapon,
+             mode,
+             team_choices,
+             ratings,
+             arena_friction,
+             level,
+             world) = data;
         let mut world = world.write().unwrap();
         for (entity, actor, _) in (&*entities, &actor, &to_spawn).join() {
+            // `ToSpawn` is only removed by `Sending`, so an actor can pass through here repeatedly
+            if world.get(&actor.id).is_some() {
+                continue;
+            }
             match actor.kind {
                 ActorKind::Player => {
-                    spawn_player(entity, actor, &mut acc, &mut vel, &mut pos, &mut world)
+                    let own_team = choose_team(
+                        &*entities,
+                        entity,
+                        actor,
+                        &team,
+                        &name,
+                        &team_choices,
+                        &ratings,
+                        &mode,
+                    );
+                    team.insert(entity, own_team);
+                    let row = teammates_in_play(&*entities, entity, own_team, &team, &pos);
+                    let (start, paddle) = if mode.is_pong() {
+                        let own_side = mode.sides()[own_team.0 as usize];
+                        side.insert(entity, own_side);
+                        (side_position(own_side, row, &level), paddle_size(own_side))
+                    } else {
+                        let start = start_position(own_team, row, &mode, &level, &world);
+                        (start, Size::paddle())
+                    };
+                    spawn_player(
+                        entity,
+                        actor,
+                        start,
+                        &paddle,
+                        &mut acc,
+                        &mut vel,
+                        &mut pos,
+                        &mut world,
+                    );
+                    size.insert(entity, paddle);
+                    match *mode {
+                        GameMode::Shooter => {
+                            health.insert(entity, Health(100));
+                            weapon.insert(entity, Weapon::default());
+                        }
+                        GameMode::Arena => {
+                            friction.insert(entity, arena_friction.clone());
+                        }
+                        GameMode::Pong | GameMode::FourPlayer => {
+                            score.insert(entity, Score(0));
+                        }
+                    }
                 }
                 ActorKind::Ball => {
                     spawn_ball(
                         entity,
                         actor,
+                        &level,
                         &mut vel,
                         &mut pos,
                         &mut bounciness,
                         &mut world,
                     )
                 }
+                ActorKind::Projectile => spawn_projectile(entity, actor, &pos, &mut world),
+                ActorKind::Pickup => spawn_pickup(entity, actor, &pos, &mut world),
             }
         }
     }
 }
 
+/// The team a player asked for, or else the one they were in before.
+/// New players join one of the smallest teams, picked to keep the teams evenly matched.
+fn choose_team(
+    entities: &EntitiesRes,
+    entity: Entity,
+    actor: &Actor,
+    teams: &WriteStorage<Team>,
+    names: &ReadStorage<Name>,
+    choices: &TeamChoices,
+    ratings: &Ratings,
+    mode: &GameMode,
+) -> Team {
+    let team_count = mode.team_count();
+    let choice = choices.read().unwrap().get(&actor.id).cloned();
+    if let Some(choice) = choice.or_else(|| teams.get(entity).cloned()) {
+        if (choice.0 as usize) < team_count {
+            return choice;
+        }
+    }
+    let rating_of = |player| names.get(player).map_or(rating::INITIAL, |name| ratings.get(name));
+    let mut members = vec![Vec::new(); team_count];
+    for (other, team) in (entities, teams).join() {
+        if other != entity && (team.0 as usize) < team_count {
+            members[team.0 as usize].push(rating_of(other));
+        }
+    }
+    Team(rating::balanced_team(rating_of(entity), &members) as u8)
+}
+
+/// Teammates that already have a body, so the new player doesn't spawn right on top of them
+fn teammates_in_play(
+    entities: &EntitiesRes,
+    entity: Entity,
+    own_team: Team,
+    teams: &WriteStorage<Team>,
+    pos: &WriteStorage<Pos>,
+) -> usize {
+    (entities, teams, pos)
+        .join()
+        .filter(|&(other, team, _)| other != entity && *team == own_team)
+        .count()
+}
+
+fn side_position(side: Side, row: usize, level: &Level) -> Vector {
+    // The front row keeps the same distance to the edge as the classic paddles
+    const INSET: i32 = 20;
+    let inset = INSET + (row % MAX_ROWS) as i32 * ROW_SPACING;
+    match side {
+        Side::Left => Vector {
+            x: inset,
+            y: level.height / 2,
+        },
+        Side::Right => Vector {
+            x: level.width - inset,
+            y: level.height / 2,
+        },
+        Side::Top => Vector {
+            x: level.width / 2,
+            y: inset,
+        },
+        Side::Bottom => Vector {
+            x: level.width / 2,
+            y: level.height - inset,
+        },
+    }
+}
+
+fn paddle_size(side: Side) -> Size {
+    if side.is_horizontal() {
+        Size::paddle().rotated()
+    } else {
+        Size::paddle()
+    }
+}
+
+fn start_position(
+    team: Team,
+    row: usize,
+    mode: &GameMode,
+    level: &Level,
+    world: &World<Id>,
+) -> Vector {
+    // Teams take turns, so teammates spread over the spawn points
+    let index = team.0 as usize + row * mode.team_count();
+    let spawn_points = if !level.spawn_points.is_empty() {
+        level.spawn_points.clone()
+    } else if *mode == GameMode::Arena {
+        let (left, top) = (level.width / 10, level.height / 10);
+        let (right, bottom) = (level.width - left, level.height - top);
+        [(left, top), (right, bottom), (right, top), (left, bottom)]
+            .iter()
+            .map(|&(x, y)| Vector { x, y })
+            .collect()
+    } else {
+        return side_position(mode.sides()[team.0 as usize], row, level);
+    };
+    // Players leaving and joining mess up the turns, so skip the points that are taken
+    let count = spawn_points.len();
+    (0..count)
+        .map(|offset| &spawn_points[(index + offset) % count])
+        .find(|point| is_free(point, &Size::paddle(), level, world))
+        .unwrap_or(&spawn_points[index % count])
+        .clone()
+}
+
+/// Whether an actor of the given size could stand at `point` without overlapping walls or players
+fn is_free(point: &Vector, size: &Size, level: &Level, world: &World<Id>) -> bool {
+    let bounds = bounds_at(point.x, point.y, size);
+    if level.overlaps_wall(&bounds) {
+        return false;
+    }
+    let mut is_free = true;
+    world.query_intersects_other(&bounds, |other| if other.filter.layer & layer::PLAYER != 0 {
+        is_free = false;
+    });
+    is_free
+}
+
+fn bounds_at(x: i32, y: i32, size: &Size) -> Bounds {
+    Bounds {
+        x,
+        y,
+        width: size.width,
+        height: size.height,
+    }
+}
+
 fn spawn_player(
     entity: Entity,
     actor: &Actor,
+    start: Vector,
+    size: &Size,
     acc: &mut WriteStorage<Acc>,
     vel: &mut WriteStorage<Vel>,
     pos: &mut WriteStorage<Pos>,
     world: &mut World<Id>,
 ) {
-    let x = if entity.id() % 2 == 0 { 20 } else { 980 };
-    let y = 500;
+    let Vector { x, y } = start;
     acc.insert(entity, Acc::from(Vector { x: 0, y: 0 }));
     vel.insert(entity, Vel::from(Vector { x: 0, y: 0 }));
     pos.insert(entity, Pos::from(Vector { x, y }));
-    let bounds = Bounds {
-        x,
-        y,
-        width: 15,
-        height: 75,
-    };
-    world.add(actor.id, bounds);
+    let bounds = bounds_at(x, y, size);
+    let filter = Filter::new(layer::PLAYER, layer::BALL | layer::WALL);
+    world.add_filtered(actor.id, bounds, filter);
 }
 
 fn spawn_ball(
     entity: Entity,
     actor: &Actor,
+    level: &Level,
     vel: &mut WriteStorage<Vel>,
     pos: &mut WriteStorage<Pos>,
     bounciness: &mut WriteStorage<Bounciness>,
     world: &mut World<Id>,
 ) {
-    let x = 500;
-    let y = 500;
-    vel.insert(entity, Vel::from(Vector { x: 7, y: 11 }));
-    pos.insert(entity, Pos::from(Vector { x, y: 500 }));
+    const SIZE: i32 = 15;
+    const ATTEMPTS: usize = 10;
+    // Several balls might be in play, so they shouldn't all take the same way.
+    // They start somewhere around the center of the level, but never inside of a wall.
+    let mut rng = rand::thread_rng();
+    let x = level.width / 2;
+    let spread = (level.height / 5).max(1);
+    let size = Size {
+        width: SIZE,
+        height: SIZE,
+    };
+    let y = (0..ATTEMPTS)
+        .map(|_| rng.gen_range(level.height / 2 - spread, level.height / 2 + spread))
+        .find(|&y| !level.overlaps_wall(&bounds_at(x, y, &size)))
+        .unwrap_or(level.height / 2);
+    let direction = if entity.id() % 2 == 0 { 1 } else { -1 };
+    let vel_y = *rng.choose(&[-11, -7, 7, 11]).unwrap();
+    vel.insert(
+        entity,
+        Vel::from(Vector {
+            x: direction * 7,
+            y: vel_y,
+        }),
+    );
+    pos.insert(entity, Pos::from(Vector { x, y }));
     bounciness.insert(entity, Bounciness {});
+    let bounds = bounds_at(x, y, &size);
+    let filter = Filter::new(
+        layer::BALL,
+        layer::PLAYER | layer::BALL | layer::WALL | layer::GOAL | layer::PICKUP,
+    );
+    world.add_filtered(actor.id, bounds, filter);
+}
+
+fn spawn_projectile(
+    entity: Entity,
+    actor: &Actor,
+    pos: &WriteStorage<Pos>,
+    world: &mut World<Id>,
+) {
+    // Projectiles are fired from wherever their shooter stands, so `Shooting` already placed them
+    let pos = pos.get(entity).expect("Tried to spawn a projectile without a position");
     let bounds = Bounds {
-        x,
-        y,
-        width: 15,
-        height: 15,
+        x: pos.x,
+        y: pos.y,
+        width: 10,
+        height: 10,
+    };
+    let filter = Filter::new(layer::PROJECTILE, layer::PLAYER | layer::WALL);
+    world.add_filtered(actor.id, bounds, filter);
+}
+
+fn spawn_pickup(entity: Entity, actor: &Actor, pos: &WriteStorage<Pos>, world: &mut World<Id>) {
+    // Pickups are dropped at random places, which `PowerUps` already decided on
+    let pos = pos.get(entity).expect("Tried to spawn a pickup without a position");
+    let bounds = Bounds {
+        x: pos.x,
+        y: pos.y,
+        width: 30,
+        height: 30,
     };
-    world.add(actor.id, bounds);
+    world.add_filtered(actor.id, bounds, Filter::trigger(layer::PICKUP, layer::BALL));
 }
@@ -11,6 +11,7 @@ ssh jnf service pixi stop &&
 SHOOTR_LOCATION=jnf:/usr/local/src/pixi &&
 echo  -e "\033[33;36m Copying files to $SHOOTR_LOCATION... \033[0m" &&
 scp target/release/shootr $SHOOTR_LOCATION &&
+scp -r core/levels $SHOOTR_LOCATION &&
 # Todo: Find out why we can't terminate the next line in '&&', as it stops the script otherwise
 scp -r web/client/public web/app.js web/server/yarn.lock web/server/package.json web/server/package-lock.json $SHOOTR_LOCATION;
 scp .env $SHOOTR_LOCATION &&
@@ -0,0 +1,70 @@
+import * as Connection from "./connection"
+import * as Types from "./types"
+
+const MAX_LINES = 8
+const JOIN_COMMAND = "/join "
+
+let log: HTMLDivElement
+let input: HTMLInputElement
+
+export function setup (): void {
+    log = document.createElement("div")
+    log.className = "chat-log"
+    input = document.createElement("input")
+    input.className = "chat-input"
+    input.placeholder = "Press Enter to chat, /join <room> to switch rooms"
+    document.body.appendChild(log)
+    document.body.appendChild(input)
+
+    input.addEventListener("keydown", (event) => {
+        // Keys typed into the chat are no game commands
+        event.stopPropagation()
+        if (event.code === "Enter") {
+            submit(input.value)
+            input.value = ""
+            input.blur()
+        } else if (event.code === "Escape") {
+            input.blur()
+        }
+    })
+    input.addEventListener("keyup", (event) => event.stopPropagation())
+}
+
+export function focus (): void {
+    input.focus()
+}
+
+export function showMessage (msg: Types.IChatMessage): void {
+    addLine(`[${msg.room}] ${msg.name}: ${msg.text}`)
+}
+
+export function showRejection (reason: string): void {
+    addLine(reason, "chat-rejected")
+}
+
+export function showJoined (room: string): void {
+    addLine(`Joined ${room}`)
+}
+
+function submit (text: string): void {
+    const trimmed = text.trim()
+    if (trimmed.startsWith(JOIN_COMMAND)) {
+        // The server confirms the room once it accepted the name
+        Connection.joinRoom(trimmed.slice(JOIN_COMMAND.length))
+    } else if (trimmed.length > 0) {
+        Connection.chat(trimmed)
+    }
+}
+
+function addLine (text: string, className?: string): void {
+    const line = document.createElement("div")
+    // Never render what other players wrote as HTML
+    line.textContent = text
+    if (className) {
+        line.className = className
+    }
+    log.appendChild(line)
+    while (log.childElementCount > MAX_LINES && log.firstChild) {
+        log.removeChild(log.firstChild)
+    }
+}
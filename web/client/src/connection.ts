@@ -1,3 +1,4 @@
+import * as Chat from "./chat"
 import * as Display from "./display"
 import * as Globals from "./globals"
 import * as Types from "./types"
@@ -5,11 +6,22 @@ import * as Types from "./types"
 let io: WebSocket | null
 const MIN_WAIT = 100
 let wait = MIN_WAIT
+let shutdownReason: string | null = null
 export function connect (address: string): void {
     io = new WebSocket(address)
     io.onopen = () => {
         resetWait()
         Globals.connectionInfo.visible = false
+        const team = numberParam("team")
+        if (team !== null) {
+            sendRequest({ opcode: "JoinTeam", payload: team })
+        }
+        const rate = numberParam("rate")
+        if (rate !== null) {
+            sendRequest({ opcode: "SetSendRate", payload: rate })
+        }
+        // The server only lets us play once we introduced ourselves
+        sendRequest({ opcode: "Hello", payload: { name: playerName() } })
     }
 
     io.onmessage = (serializedMsg) => {
@@ -18,22 +30,69 @@ export function connect (address: string): void {
         switch (msg.opcode) {
         case Types.OpCode.Greeting:
             Globals.setOwnId(msg.payload[0])
+            Display.drawLevel(msg.payload[2])
             const presentActors = msg.payload[1]
             for (const actor of presentActors) {
                 Display.spawnActor(actor)
             }
             break
         case Types.OpCode.Spawn:
+            if (msg.payload.id === Globals.ownId) {
+                Globals.connectionInfo.visible = false
+            }
             Display.spawnActor(msg.payload)
             break
         case Types.OpCode.Despawn:
             Display.removeActor(msg.payload)
             break
+        case Types.OpCode.Damage:
+            Display.showDamage(msg.payload as Types.IDamageReport)
+            break
+        case Types.OpCode.Death:
+            const death: Types.IDeathReport = msg.payload
+            if (death.id === Globals.ownId) {
+                Globals.connectionInfo.text = "Respawning..."
+                Globals.connectionInfo.visible = true
+            }
+            break
+        case Types.OpCode.Ping:
+            const ping: Types.IPing = msg.payload
+            sendRequest({ opcode: "Pong", payload: { sent: ping.sent, received: performance.now() } })
+            Globals.clock.referenceServerTime = ping.sent
+            Globals.clock.referenceTick = ping.tick
+            break
+        case Types.OpCode.Latency:
+            const latency: Types.ILatencyReport = msg.payload
+            Globals.clock.offset = latency.clock_offset
+            Globals.clock.tickRate = latency.tick_rate
+            Globals.clock.updateRate = latency.update_rate
+            break
+        case Types.OpCode.Shutdown:
+            shutdownReason = msg.payload
+            Globals.connectionInfo.text = `Server shut down: ${shutdownReason}`
+            Globals.connectionInfo.visible = true
+            break
+        case Types.OpCode.Chat:
+            Chat.showMessage(msg.payload as Types.IChatMessage)
+            break
+        case Types.OpCode.ChatRejected:
+            Chat.showRejection(msg.payload)
+            break
+        case Types.OpCode.RoomJoined:
+            Chat.showJoined(msg.payload)
+            break
         case Types.OpCode.WorldUpdate:
+            if (msg.tick === undefined) {
+                throw new Error("Received a world update without a tick")
+            }
+            showPause(msg.payload.paused, msg.tick)
+            // Actors that were left out to save bandwidth keep their last known state
+            const previous = Globals.states[Globals.states.length - 1]
+            const actors = Object.assign({}, previous ? knownActors(previous) : {}, msg.payload.actors)
             const state: Types.IState = {
-                actors: msg.payload.actors,
+                actors,
                 tick: msg.tick,
-                timestamp: performance.now(),
+                timestamp: tickTime(msg.tick),
             }
             Globals.states.push(state)
             const index = Globals.unconfirmedInputs.findIndex((input) => input.id === msg.payload.last_input) + 1
@@ -47,7 +106,10 @@ export function connect (address: string): void {
     }
 
     io.onclose = () => {
-        Globals.connectionInfo.text = "Attempting to reconnect"
+        Globals.connectionInfo.text = shutdownReason
+            ? `Server shut down: ${shutdownReason}, attempting to reconnect`
+            : "Attempting to reconnect"
+        shutdownReason = null
         Globals.connectionInfo.visible = true
         io = null
         setTimeout(() => {
@@ -70,9 +132,78 @@ export function connect (address: string): void {
 }
 
 export function send (data: Types.IClientMessage): void {
+    sendRequest({ opcode: "Input", payload: data })
+}
+
+export function votePause (vote: boolean): void {
+    sendRequest({ opcode: "VotePause", payload: vote })
+}
+
+export function chat (text: string): void {
+    sendRequest({ opcode: "Chat", payload: text })
+}
+
+export function joinRoom (room: string): void {
+    sendRequest({ opcode: "JoinRoom", payload: room })
+}
+
+function sendRequest (request: Types.IClientRequest): void {
     if (io && io.readyState === 1) {
-        io.send(JSON.stringify(data))
+        io.send(JSON.stringify(request))
+    }
+}
+
+// Number given in the URL, e.g. `?team=1`
+function numberParam (name: string): number | null {
+    const match = new RegExp(`[?&]${name}=(\\d+)`).exec(window.location.search)
+    return match ? parseInt(match[1], 10) : null
+}
+
+// Name given in the URL, e.g. `?name=Ann`, otherwise the one used last time.
+// Without any, the server makes one up.
+function playerName (): string {
+    const match = /[?&]name=([^&]*)/.exec(window.location.search)
+    if (match) {
+        const name = decodeURIComponent(match[1].replace(/\+/g, " "))
+        localStorage.setItem("name", name)
+        return name
+    }
+    return localStorage.getItem("name") || ""
+}
+
+const PAUSED_TEXT = "Paused"
+function showPause (paused: boolean, tick: number): void {
+    if (paused) {
+        Globals.connectionInfo.text = PAUSED_TEXT
+        Globals.connectionInfo.visible = true
+        // Ticks stand still during a pause, so they have to be lined up with the time again
+        if (Globals.clock.offset !== null) {
+            Globals.clock.referenceTick = tick
+            Globals.clock.referenceServerTime = performance.now() - Globals.clock.offset
+        }
+    } else if (Globals.connectionInfo.text === PAUSED_TEXT) {
+        Globals.connectionInfo.visible = false
+    }
+}
+
+// Client time at which the server produced `tick`, as far as we know
+function tickTime (tick: number): number {
+    const clock = Globals.clock
+    if (clock.offset === null) {
+        return performance.now()
+    }
+    const serverTime = clock.referenceServerTime + (tick - clock.referenceTick) * 1000 / clock.tickRate
+    return serverTime + clock.offset
+}
+
+function knownActors (state: Types.IState): { [id: string]: Types.IActor } {
+    const known: { [id: string]: Types.IActor } = {}
+    for (const id of Object.keys(state.actors)) {
+        if (Globals.actors.has(id)) {
+            known[id] = (state.actors as any)[id]
+        }
     }
+    return known
 }
 
 function resetWait (): void {
@@ -25,21 +25,39 @@ export function setup (gameLoop: (delta: number) => void): void {
 }
 
 const resources = PIXI.loader.resources
+function teamTint (team: Types.Team | null | undefined): number {
+    const TINTS = [0xffffff, 0x6fa8ff, 0xff7f7f, 0xffd27f]
+    return team === null || team === undefined ? 0xffffff : TINTS[team % TINTS.length]
+}
+
 export function spawnActor (actor: Types.IActor): void {
     let texture: string
     let height: number
     let width: number
+    let tint = 0xffffff
     switch (actor.kind) {
     case Types.ActorKind.Player:
         texture = "fancy-paddle-green.png"
         height = 75
         width = 15
+        tint = teamTint(actor.team)
         break
     case Types.ActorKind.Ball:
         texture = "fancy-ball.png"
         height = 15
         width = 15
         break
+    case Types.ActorKind.Projectile:
+        texture = "fancy-ball.png"
+        height = 10
+        width = 10
+        break
+    case Types.ActorKind.Pickup:
+        texture = "fancy-ball.png"
+        height = 30
+        width = 30
+        tint = 0xffd700
+        break
     default:
         throw new Error(`Tried to spawn invalid kind of actor: ${actor.kind}`)
     }
@@ -52,8 +70,25 @@ export function spawnActor (actor: Types.IActor): void {
     sprite.anchor.set(0.5)
     sprite.width = width
     sprite.height = height
+    sprite.tint = tint
     app.stage.addChild(sprite)
     Globals.actors.set(actor.id, sprite)
+
+    if (actor.name) {
+        const nameTag = new PIXI.Text(actor.name, { fontSize: 14, fill: tint === 0xffffff ? 0xe3e3ed : tint })
+        nameTag.anchor.set(0.5, 1)
+        app.stage.addChild(nameTag)
+        Globals.nameTags.set(actor.id, nameTag)
+    }
+}
+
+// Keeps the name right above the actor
+export function placeNameTag (id: Types.Id, actor: PIXI.Sprite): void {
+    const nameTag = Globals.nameTags.get(id)
+    if (nameTag) {
+        nameTag.x = actor.x
+        nameTag.y = actor.y - actor.height / 2 - 5
+    }
 }
 
 export function removeActor (id: Types.Id): void {
@@ -63,6 +98,42 @@ export function removeActor (id: Types.Id): void {
     }
     app.stage.removeChild(actor)
     Globals.actors.delete(id)
+    const nameTag = Globals.nameTags.get(id)
+    if (nameTag) {
+        app.stage.removeChild(nameTag)
+        Globals.nameTags.delete(id)
+    }
+}
+
+let level: PIXI.Graphics | null = null
+export function drawLevel (newLevel: Types.ILevel): void {
+    if (level) {
+        app.stage.removeChild(level)
+    }
+    level = new PIXI.Graphics()
+    for (const goal of newLevel.goals) {
+        drawBounds(level, goal.bounds, 0xe3e3ed, 0.3)
+    }
+    for (const wall of newLevel.walls) {
+        drawBounds(level, wall, 0x555566, 1)
+    }
+    // Keep the level right above the background, below every actor
+    app.stage.addChildAt(level, 1)
+}
+
+function drawBounds (graphics: PIXI.Graphics, bounds: Types.IBounds, color: number, alpha: number): void {
+    graphics.beginFill(color, alpha)
+    graphics.drawRect(bounds.x - bounds.width / 2, bounds.y - bounds.height / 2, bounds.width, bounds.height)
+    graphics.endFill()
+}
+
+export function showDamage (report: Types.IDamageReport): void {
+    const actor = Globals.actors.get(report.id)
+    if (!actor) {
+        return
+    }
+    actor.tint = 0xff0000
+    setTimeout(() => actor.tint = 0xffffff, 100)
 }
 
 export function setBlur (obj: PIXI.Sprite, vel: Types.IVector): void {
@@ -1,6 +1,7 @@
 import * as Types from "./types"
 
 export const actors = new Map<Types.Id, PIXI.Sprite>()
+export const nameTags = new Map<Types.Id, PIXI.Text>()
 export let ownId: Types.Id
 export function setOwnId (id: Types.Id): void {
     ownId = id
@@ -11,3 +12,13 @@ export function setConnectionInfo (sprite: PIXI.Text): void {
 }
 export const states: Types.IState[] = []
 export const unconfirmedInputs: Types.IClientMessage[] = []
+
+// Relation between server ticks and client time, learned from pings
+export const clock = {
+    // Client time minus server time in ms, unknown until the first ping got answered
+    offset: null as number | null,
+    referenceServerTime: 0,
+    referenceTick: 0,
+    tickRate: 30,
+    updateRate: 30,
+}
@@ -1,5 +1,6 @@
 "use strict"
 
+import * as Chat from "./chat"
 import * as Display from "./display"
 import * as Globals from "./globals"
 import * as Input from "./input"
@@ -8,6 +9,7 @@ import * as Types from "./types"
 
 Display.setup(gameLoop)
 Input.setup()
+Chat.setup()
 
 let onGameUpdate = connecting
 function gameLoop (delta: number): void {
@@ -53,8 +55,13 @@ function setWorld (state: Types.IState): void {
         }
         liveActor.x = stateActor.pos.x
         liveActor.y = stateActor.pos.y
+        if (stateActor.size) {
+            liveActor.width = stateActor.size.width
+            liveActor.height = stateActor.size.height
+        }
         if (stateActor.vel) {
             Display.setBlur(liveActor, stateActor.vel)
         }
+        Display.placeNameTag(id, liveActor)
     }
 }
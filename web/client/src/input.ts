@@ -1,11 +1,22 @@
+import * as Chat from "./chat"
 import * as Connection from "./connection"
 import * as Globals from "./globals"
 import * as Types from "./types"
 
 const commandState = new Map<Types.Command, boolean>()
+let votesPause = false
 
 export function setup () {
     document.addEventListener("keydown", (event) => {
+        if (event.code === "Enter") {
+            Chat.focus()
+            return
+        }
+        if (event.code === "KeyP" && !event.repeat) {
+            votesPause = !votesPause
+            Connection.votePause(votesPause)
+            return
+        }
         const command = codeToEvent(event.code)
         if (command) {
             sendCommand(command, true)
@@ -27,6 +38,14 @@ function codeToEvent (code: string): Types.Command | null {
     case "KeyS":
     case "ArrowDown":
         return Types.Command.MoveDown
+    case "KeyA":
+    case "ArrowLeft":
+        return Types.Command.MoveLeft
+    case "KeyD":
+    case "ArrowRight":
+        return Types.Command.MoveRight
+    case "Space":
+        return Types.Command.Shoot
     default:
         return null
     }
@@ -3,8 +3,7 @@ import * as Types from "./types"
 
 export function getRenderTime (): number {
     const lerpRatio = 2
-    const updateRate = 30
-    const delay = Math.floor(lerpRatio * 1000 / updateRate)
+    const delay = Math.floor(lerpRatio * 1000 / Globals.clock.updateRate)
     const now = Math.floor(performance.now())
     return now - delay
 }
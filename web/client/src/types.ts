@@ -5,13 +5,18 @@ export interface IState {
 }
 
 export type Id = string
+export type Team = number
 export interface IActor {
     id: Id,
-    kind: ActorKind
+    kind: ActorKind,
+    team?: Team | null,
+    name?: string | null,
 }
 export enum ActorKind {
     Player = "Player",
     Ball = "Ball",
+    Projectile = "Projectile",
+    Pickup = "Pickup",
 }
 
 export enum OpCode {
@@ -19,11 +24,22 @@ export enum OpCode {
     Spawn = "Spawn",
     Despawn = "Despawn",
     WorldUpdate = "WorldUpdate",
+    Damage = "Damage",
+    Death = "Death",
+    Ping = "Ping",
+    Latency = "Latency",
+    Shutdown = "Shutdown",
+    Chat = "Chat",
+    ChatRejected = "ChatRejected",
+    RoomJoined = "RoomJoined",
 }
 
 export enum Command {
     MoveUp = "MoveUp",
     MoveDown = "MoveDown",
+    MoveLeft = "MoveLeft",
+    MoveRight = "MoveRight",
+    Shoot = "Shoot",
 }
 
 export interface IClientMessage {
@@ -32,10 +48,82 @@ export interface IClientMessage {
     id: number
 }
 
+export type IClientRequest =
+    { opcode: "Hello", payload: IHello } |
+    { opcode: "Input", payload: IClientMessage } |
+    { opcode: "JoinTeam", payload: Team } |
+    { opcode: "SetSendRate", payload: number } |
+    { opcode: "Pong", payload: IPong } |
+    { opcode: "VotePause", payload: boolean } |
+    { opcode: "Chat", payload: string } |
+    { opcode: "JoinRoom", payload: string }
+
+export interface IHello {
+    name: string,
+}
+
 export interface IServerMessage {
     opcode: OpCode,
     payload: any,
-    tick: number
+    // Missing on chat messages, which don't belong to a tick
+    tick?: number
+}
+
+export interface IChatMessage {
+    room: string,
+    sender: Id,
+    name: string,
+    text: string,
+    sent: number,
+}
+
+export interface IPing {
+    sent: number,
+    tick: number,
+}
+
+export interface IPong {
+    sent: number,
+    received: number,
+}
+
+export interface ILatencyReport {
+    rtt: number,
+    clock_offset: number,
+    tick_rate: number,
+    update_rate: number,
+}
+
+export interface IDamageReport {
+    id: Id,
+    source: Id,
+    amount: number,
+    health: number,
+}
+
+export interface IDeathReport {
+    id: Id,
+    killer: Id,
+}
+
+export interface IBounds {
+    x: number,
+    y: number,
+    width: number,
+    height: number,
+}
+
+export interface IGoal {
+    bounds: IBounds,
+    side: string,
+}
+
+export interface ILevel {
+    width: number,
+    height: number,
+    walls: IBounds[],
+    goals: IGoal[],
+    spawn_points: IVector[],
 }
 
 export interface IVector {